@@ -0,0 +1,106 @@
+use aes::Aes128;
+use ccm::{
+    aead::{generic_array::GenericArray, AeadInPlace, KeyInit},
+    consts::{U13, U4},
+    Ccm,
+};
+
+use super::BTHOME_UUID;
+
+/// AES-CCM with the 4 byte tag and 13 byte nonce mandated by BTHome.
+type BtHomeCcm = Ccm<Aes128, U4, U13>;
+
+/// The encryption state of a BTHome advertisement.
+///
+/// It is not `Clone`, because two copies would reuse the same nonces.
+pub(crate) struct Encryption {
+    bind_key: [u8; 16],
+    address: [u8; 6],
+    counter: u32,
+}
+
+impl Encryption {
+    pub(crate) const fn new(bind_key: [u8; 16], address: [u8; 6], counter: u32) -> Self {
+        Self {
+            bind_key,
+            address,
+            counter,
+        }
+    }
+
+    /// Returns the counter value of the next encrypted advertisement.
+    pub(crate) const fn counter(&self) -> u32 {
+        self.counter
+    }
+
+    /// Encrypts the payload in place, and returns the trailing counter and message integrity check.
+    ///
+    /// The counter is incremented on every call, so that no nonce is ever reused.
+    pub(crate) fn encrypt(&mut self, device_info: u8, payload: &mut [u8]) -> [u8; 8] {
+        let counter = self.counter.to_le_bytes();
+        self.counter = self.counter.wrapping_add(1);
+
+        // Nonce: MAC address, UUID, device information and counter.
+        let mut nonce = [0u8; 13];
+        nonce[..6].copy_from_slice(&self.address);
+        nonce[6..8].copy_from_slice(&BTHOME_UUID.to_le_bytes());
+        nonce[8] = device_info;
+        nonce[9..].copy_from_slice(&counter);
+
+        let cipher = BtHomeCcm::new(GenericArray::from_slice(&self.bind_key));
+        let tag = cipher
+            .encrypt_in_place_detached(GenericArray::from_slice(&nonce), &[], payload)
+            .expect("BTHome payload is too long to be encrypted.");
+
+        let mut trailer = [0u8; 8];
+        trailer[..4].copy_from_slice(&counter);
+        trailer[4..].copy_from_slice(&tag);
+        trailer
+    }
+}
+
+impl std::fmt::Debug for Encryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print the bind key.
+        f.debug_struct("Encryption")
+            .field("address", &self.address)
+            .field("counter", &self.counter)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decrypts_with_the_same_nonce() {
+        let bind_key = [0x23; 16];
+        let address = [0x54, 0x48, 0xE6, 0x8F, 0x80, 0xA5];
+        let mut encryption = Encryption::new(bind_key, address, 0x3322_1100);
+
+        let plain = [0x02, 0xCA, 0x09, 0x03, 0xBF, 0x13];
+        let mut payload = plain;
+        let trailer = encryption.encrypt(0x41, &mut payload);
+
+        assert_ne!(payload, plain);
+        assert_eq!(trailer[..4], [0x00, 0x11, 0x22, 0x33]);
+        assert_eq!(encryption.counter(), 0x3322_1101);
+
+        let mut nonce = [0u8; 13];
+        nonce[..6].copy_from_slice(&address);
+        nonce[6..8].copy_from_slice(&BTHOME_UUID.to_le_bytes());
+        nonce[8] = 0x41;
+        nonce[9..].copy_from_slice(&trailer[..4]);
+
+        BtHomeCcm::new(GenericArray::from_slice(&bind_key))
+            .decrypt_in_place_detached(
+                GenericArray::from_slice(&nonce),
+                &[],
+                &mut payload,
+                GenericArray::from_slice(&trailer[4..]),
+            )
+            .unwrap();
+        assert_eq!(payload, plain);
+    }
+}
@@ -0,0 +1,157 @@
+/// A button event, as defined by the BTHome specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ButtonEvent {
+    /// No event happened since the last advertisement.
+    None = 0x00,
+    /// A single press.
+    Press = 0x01,
+    /// A double press.
+    DoublePress = 0x02,
+    /// A triple press.
+    TriplePress = 0x03,
+    /// A long press.
+    LongPress = 0x04,
+    /// A long double press.
+    LongDoublePress = 0x05,
+    /// A long triple press.
+    LongTriplePress = 0x06,
+    /// The button is being held down.
+    HoldPress = 0x80,
+}
+
+/// A typed BTHome measurement.
+///
+/// Floating point values are expressed in the unit documented on each variant,
+/// and are scaled to the integer representation mandated by the BTHome v2 format when encoded.
+/// Values outside of the representable range are saturated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Measurement {
+    /// Packet identifier, used by receivers to discard duplicate advertisements.
+    PacketId(u8),
+    /// Battery level, in percent.
+    Battery(u8),
+    /// Temperature, in degrees Celsius, with a resolution of 0.01 °C.
+    Temperature(f32),
+    /// Relative humidity, in percent, with a resolution of 0.01 %.
+    Humidity(f32),
+    /// Atmospheric pressure, in hectopascal, with a resolution of 0.01 hPa.
+    Pressure(f32),
+    /// Illuminance, in lux, with a resolution of 0.01 lx.
+    Illuminance(f32),
+    /// Voltage, in volts, with a resolution of 1 mV.
+    Voltage(f32),
+    /// Carbon dioxide concentration, in parts per million.
+    Co2(u16),
+    /// Soil moisture, in percent, with a resolution of 0.01 %.
+    Moisture(f32),
+    /// A generic counter.
+    Count(u32),
+    /// A button event.
+    Button(ButtonEvent),
+}
+
+impl Measurement {
+    /// Returns the BTHome object identifier of this [`Measurement`].
+    #[must_use]
+    pub const fn object_id(&self) -> u8 {
+        match self {
+            Self::PacketId(_) => 0x00,
+            Self::Battery(_) => 0x01,
+            Self::Temperature(_) => 0x02,
+            Self::Humidity(_) => 0x03,
+            Self::Pressure(_) => 0x04,
+            Self::Illuminance(_) => 0x05,
+            Self::Voltage(_) => 0x0C,
+            Self::Co2(_) => 0x12,
+            Self::Moisture(_) => 0x14,
+            Self::Button(_) => 0x3A,
+            Self::Count(_) => 0x3E,
+        }
+    }
+
+    /// Appends the object identifier and the little-endian value to the buffer.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub(crate) fn encode_into(&self, buffer: &mut Vec<u8>) {
+        buffer.push(self.object_id());
+
+        match *self {
+            Self::PacketId(value) | Self::Battery(value) => buffer.push(value),
+            Self::Temperature(value) => {
+                let scaled = scale(value, 0.01, f64::from(i16::MIN), f64::from(i16::MAX)) as i16;
+                buffer.extend_from_slice(&scaled.to_le_bytes());
+            }
+            Self::Humidity(value) | Self::Moisture(value) => {
+                let scaled = scale(value, 0.01, 0.0, f64::from(u16::MAX)) as u16;
+                buffer.extend_from_slice(&scaled.to_le_bytes());
+            }
+            Self::Pressure(value) | Self::Illuminance(value) => {
+                let scaled = scale(value, 0.01, 0.0, f64::from(0x00FF_FFFF)) as u32;
+                buffer.extend_from_slice(&scaled.to_le_bytes()[..3]);
+            }
+            Self::Voltage(value) => {
+                let scaled = scale(value, 0.001, 0.0, f64::from(u16::MAX)) as u16;
+                buffer.extend_from_slice(&scaled.to_le_bytes());
+            }
+            Self::Co2(value) => buffer.extend_from_slice(&value.to_le_bytes()),
+            Self::Count(value) => buffer.extend_from_slice(&value.to_le_bytes()),
+            Self::Button(event) => buffer.push(event as u8),
+        }
+    }
+}
+
+/// Divides the value by the BTHome scaling factor, rounding and saturating the result.
+fn scale(value: f32, factor: f64, min: f64, max: f64) -> f64 {
+    (f64::from(value) / factor).round().clamp(min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(measurement: Measurement) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        measurement.encode_into(&mut buffer);
+        buffer
+    }
+
+    #[test]
+    fn encodes_scaled_values() {
+        assert_eq!(encode(Measurement::Temperature(25.06)), [0x02, 0xCA, 0x09]);
+        assert_eq!(encode(Measurement::Temperature(-1.0)), [0x02, 0x9C, 0xFF]);
+        assert_eq!(encode(Measurement::Humidity(50.55)), [0x03, 0xBF, 0x13]);
+        assert_eq!(
+            encode(Measurement::Pressure(1008.83)),
+            [0x04, 0x13, 0x8A, 0x01]
+        );
+        assert_eq!(encode(Measurement::Voltage(3.074)), [0x0C, 0x02, 0x0C]);
+    }
+
+    #[test]
+    fn encodes_integer_values() {
+        assert_eq!(encode(Measurement::PacketId(9)), [0x00, 0x09]);
+        assert_eq!(encode(Measurement::Battery(97)), [0x01, 0x61]);
+        assert_eq!(encode(Measurement::Co2(1250)), [0x12, 0xE2, 0x04]);
+        assert_eq!(
+            encode(Measurement::Count(0x0102_0304)),
+            [0x3E, 0x04, 0x03, 0x02, 0x01]
+        );
+        assert_eq!(
+            encode(Measurement::Button(ButtonEvent::LongPress)),
+            [0x3A, 0x04]
+        );
+    }
+
+    #[test]
+    fn saturates_out_of_range_values() {
+        assert_eq!(encode(Measurement::Temperature(1000.0)), [0x02, 0xFF, 0x7F]);
+        assert_eq!(
+            encode(Measurement::Temperature(-1000.0)),
+            [0x02, 0x00, 0x80]
+        );
+        assert_eq!(encode(Measurement::Humidity(-5.0)), [0x03, 0x00, 0x00]);
+        assert_eq!(
+            encode(Measurement::Illuminance(1e9)),
+            [0x05, 0xFF, 0xFF, 0xFF]
+        );
+    }
+}
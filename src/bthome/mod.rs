@@ -0,0 +1,256 @@
+//! BTHome v2 advertisements.
+//!
+//! [BTHome](https://bthome.io) is the native Bluetooth sensor format of Home Assistant.
+//! Measurements are broadcast as service data with the `0xFCD2` UUID in the advertisement packets,
+//! and can optionally be encrypted with AES-CCM and a per-device bind key.
+//!
+//! # Notes
+//!
+//! Advertisement packets are limited to 31 bytes, shared with the device name, the flags,
+//! the appearance and the TX power. Keep the device name short, or some objects might not fit.
+
+#![allow(clippy::doc_markdown)]
+
+use std::sync::{Arc, RwLock};
+
+use esp_idf_sys::{esp_mac_type_t_ESP_MAC_BT, esp_nofail, esp_read_mac};
+use log::debug;
+
+use crate::gatt_server::GLOBAL_GATT_SERVER;
+
+pub use measurement::{ButtonEvent, Measurement};
+
+mod encryption;
+mod measurement;
+
+use encryption::Encryption;
+
+/// The 16-bit service UUID assigned to BTHome.
+pub(crate) const BTHOME_UUID: u16 = 0xFCD2;
+
+/// The BTHome format version, placed in the upper bits of the device information byte.
+const BTHOME_VERSION: u8 = 2;
+
+/// A BTHome v2 advertisement.
+///
+/// Declare the objects to be broadcast, then call [`BtHome::advertise`] to put them in the GAP advertisement.
+/// Readings can be updated at any time with [`BtHome::set`], followed by another call to [`BtHome::advertise`].
+///
+/// It is not `Clone`, so that the encryption counter of an advertisement can never be duplicated.
+#[derive(Debug, Default)]
+pub struct BtHome {
+    measurements: Vec<Measurement>,
+    trigger_based: bool,
+    encryption: Option<Encryption>,
+}
+
+impl BtHome {
+    /// Creates a new [`BtHome`] advertisement, without any object.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an object to the [`BtHome`] advertisement.
+    ///
+    /// The same kind of measurement can be added more than once, for example for devices with multiple buttons.
+    pub fn measurement(&mut self, measurement: Measurement) -> &mut Self {
+        self.measurements.push(measurement);
+        self
+    }
+
+    /// Updates the first object with the same object identifier, or adds it if it is not present.
+    pub fn set(&mut self, measurement: Measurement) -> &mut Self {
+        self.set_nth(0, measurement)
+    }
+
+    /// Updates the `index`-th object with the same object identifier, or adds it if it is not present.
+    pub fn set_nth(&mut self, index: usize, measurement: Measurement) -> &mut Self {
+        if let Some(existing) = self
+            .measurements
+            .iter_mut()
+            .filter(|m| m.object_id() == measurement.object_id())
+            .nth(index)
+        {
+            *existing = measurement;
+        } else {
+            self.measurements.push(measurement);
+        }
+
+        self
+    }
+
+    /// Removes all the objects from the [`BtHome`] advertisement.
+    pub fn clear(&mut self) -> &mut Self {
+        self.measurements.clear();
+        self
+    }
+
+    /// Flags the device as trigger based, meaning that it only advertises on events.
+    ///
+    /// Receivers use this to avoid marking the device as unavailable between advertisements.
+    pub fn trigger_based(&mut self) -> &mut Self {
+        self.trigger_based = true;
+        self
+    }
+
+    /// Enables encryption with the given bind key and initial counter value.
+    ///
+    /// The Bluetooth MAC address of the device is used in the nonce,
+    /// so the device must advertise with its public address.
+    ///
+    /// The counter is part of the nonce, and receivers reject counters lower than the last one
+    /// they accepted. It must never go back under the same bind key, so persist [`BtHome::counter`]
+    /// and restore it on boot.
+    pub fn encrypted(&mut self, bind_key: [u8; 16], counter: u32) -> &mut Self {
+        let mut address = [0u8; 6];
+        unsafe {
+            esp_nofail!(esp_read_mac(
+                address.as_mut_ptr(),
+                esp_mac_type_t_ESP_MAC_BT
+            ));
+        }
+
+        self.encrypted_with_address(bind_key, address, counter)
+    }
+
+    /// Enables encryption with the given bind key, advertised address and initial counter value.
+    ///
+    /// The address is in the usual human-readable order, most significant byte first.
+    /// The counter is incremented for every encoded advertisement. Receivers reject counters
+    /// lower than the last one they accepted, so it must be persisted across reboots.
+    pub fn encrypted_with_address(
+        &mut self,
+        bind_key: [u8; 16],
+        address: [u8; 6],
+        counter: u32,
+    ) -> &mut Self {
+        self.encryption = Some(Encryption::new(bind_key, address, counter));
+        self
+    }
+
+    /// Returns the counter value of the next encrypted advertisement, if encryption is enabled.
+    ///
+    /// Persist it, or a value ahead of it, to restore it with [`BtHome::encrypted`] on boot.
+    #[must_use]
+    pub fn counter(&self) -> Option<u32> {
+        self.encryption.as_ref().map(Encryption::counter)
+    }
+
+    /// Encodes the [`BtHome`] advertisement into service data, including the leading UUID.
+    ///
+    /// Objects are sorted by object identifier, as recommended by the specification.
+    pub fn encode(&mut self) -> Vec<u8> {
+        let mut measurements = self.measurements.clone();
+        measurements.sort_by_key(Measurement::object_id);
+
+        let mut payload = Vec::new();
+        for measurement in &measurements {
+            measurement.encode_into(&mut payload);
+        }
+
+        let mut device_info = BTHOME_VERSION << 5;
+        if self.trigger_based {
+            device_info |= 0b0000_0100;
+        }
+        if self.encryption.is_some() {
+            device_info |= 0b0000_0001;
+        }
+
+        let mut service_data = BTHOME_UUID.to_le_bytes().to_vec();
+        service_data.push(device_info);
+
+        if let Some(encryption) = &mut self.encryption {
+            let trailer = encryption.encrypt(device_info, &mut payload);
+            service_data.extend_from_slice(&payload);
+            service_data.extend_from_slice(&trailer);
+        } else {
+            service_data.extend_from_slice(&payload);
+        }
+
+        service_data
+    }
+
+    /// Encodes the [`BtHome`] advertisement and puts it into the GAP advertisement data.
+    ///
+    /// If the server is already advertising, the advertisement data is updated in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the global GATT server lock is poisoned.
+    pub fn advertise(&mut self) {
+        let service_data = self.encode();
+        debug!("Advertising BTHome service data {:02X?}.", service_data);
+
+        GLOBAL_GATT_SERVER
+            .lock()
+            .unwrap()
+            .advertise_service_data(service_data);
+    }
+
+    /// Returns a reference to the built [`BtHome`] advertisement behind an `Arc` and an `RwLock`.
+    ///
+    /// It can be shared with the threads that sample the sensors.
+    /// The objects and the encryption state are moved out, and this [`BtHome`] is left empty.
+    #[must_use]
+    pub fn build(&mut self) -> Arc<RwLock<Self>> {
+        Arc::new(RwLock::new(std::mem::take(self)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_sorted_objects() {
+        let mut bthome = BtHome::new();
+        bthome
+            .measurement(Measurement::Humidity(50.55))
+            .measurement(Measurement::Temperature(25.06));
+
+        assert_eq!(
+            bthome.encode(),
+            [0xD2, 0xFC, 0x40, 0x02, 0xCA, 0x09, 0x03, 0xBF, 0x13]
+        );
+    }
+
+    #[test]
+    fn encodes_trigger_based_flag() {
+        let mut bthome = BtHome::new();
+        bthome.trigger_based().measurement(Measurement::Battery(50));
+
+        assert_eq!(bthome.encode(), [0xD2, 0xFC, 0x44, 0x01, 0x32]);
+    }
+
+    #[test]
+    fn set_replaces_the_object_with_the_same_identifier() {
+        let mut bthome = BtHome::new();
+        bthome
+            .measurement(Measurement::Battery(10))
+            .set(Measurement::Battery(20))
+            .set_nth(1, Measurement::Battery(30));
+
+        assert_eq!(bthome.encode(), [0xD2, 0xFC, 0x40, 0x01, 0x14, 0x01, 0x1E]);
+    }
+
+    #[test]
+    fn encrypts_with_counter_and_tag() {
+        let mut bthome = BtHome::new();
+        bthome
+            .measurement(Measurement::Temperature(25.06))
+            .encrypted_with_address([0x11; 16], [0x54, 0x48, 0xE6, 0x8F, 0x80, 0xA5], 7);
+
+        let first = bthome.encode();
+        let second = bthome.encode();
+
+        // UUID, device information, encrypted object, counter and tag.
+        assert_eq!(first.len(), 2 + 1 + 3 + 4 + 4);
+        assert_eq!(&first[..3], [0xD2, 0xFC, 0x41]);
+        assert_eq!(&first[6..10], 7u32.to_le_bytes());
+        assert_eq!(&second[6..10], 8u32.to_le_bytes());
+        assert_ne!(&first[3..6], [0x02, 0xCA, 0x09]);
+        assert_ne!(first[3..6], second[3..6]);
+        assert_eq!(bthome.counter(), Some(9));
+    }
+}
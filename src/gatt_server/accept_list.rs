@@ -0,0 +1,150 @@
+use esp_idf_sys::*;
+use log::{debug, info, warn};
+
+use crate::{
+    gatt_server::GattServer,
+    utilities::{BleAddress, BleAddressType, FilterPolicy},
+};
+
+impl GattServer {
+    /// Adds a device to the controller's filter accept list.
+    ///
+    /// Peers using resolvable private addresses must be added with their identity address.
+    /// Before the server starts, the device is only recorded, and added when the stack is running.
+    pub fn accept_list_add(&mut self, address: BleAddress) -> &mut Self {
+        if self.accept_list.contains(&address) {
+            debug!("{:?} is already in the filter accept list.", address);
+            return self;
+        }
+
+        if address.is_resolvable_private() {
+            warn!(
+                "Adding resolvable private address {} to the filter accept list. It will stop matching when it rotates.",
+                address
+            );
+        }
+
+        if self.started {
+            let mut size = 0u16;
+            unsafe {
+                esp_nofail!(esp_ble_gap_get_whitelist_size(&mut size));
+            }
+
+            if self.accept_list.len() >= size as usize {
+                warn!(
+                    "Filter accept list is full ({} entries). Cannot add {}.",
+                    size, address
+                );
+                return self;
+            }
+
+            Self::update_accept_list(true, address);
+        }
+
+        self.accept_list.push(address);
+        self
+    }
+
+    /// Removes a device from the controller's filter accept list.
+    pub fn accept_list_remove(&mut self, address: BleAddress) -> &mut Self {
+        let Some(index) = self.accept_list.iter().position(|a| *a == address) else {
+            debug!("{:?} is not in the filter accept list.", address);
+            return self;
+        };
+
+        if self.started {
+            Self::update_accept_list(false, address);
+        }
+
+        self.accept_list.remove(index);
+        self
+    }
+
+    /// Removes all the devices from the controller's filter accept list.
+    pub fn accept_list_clear(&mut self) -> &mut Self {
+        if self.started {
+            unsafe {
+                esp_nofail!(esp_ble_gap_clear_whitelist());
+            }
+        }
+
+        self.accept_list.clear();
+        self
+    }
+
+    /// Adds all the bonded peers to the controller's filter accept list, with their identity address.
+    pub fn accept_list_from_bonds(&mut self) -> &mut Self {
+        for address in Self::bonded_devices() {
+            self.accept_list_add(address);
+        }
+
+        self
+    }
+
+    /// Returns the devices in the filter accept list.
+    #[must_use]
+    pub fn accept_list(&self) -> Vec<BleAddress> {
+        self.accept_list.clone()
+    }
+
+    /// Sets the advertising filter policy, deciding whether the filter accept list is used for scans and connections.
+    ///
+    /// The policy is also set by [`GattServer::gap_mode`], and stays in effect until the next mode change.
+    pub fn filter_policy(&mut self, policy: FilterPolicy) -> &mut Self {
+        self.advertisement_parameters.adv_filter_policy = policy.into();
+
+        if self.started && self.advertisement_configured {
+            // The advertising parameters are applied when the advertisement restarts.
+            unsafe {
+                esp_nofail!(esp_ble_gap_stop_advertising());
+                esp_nofail!(esp_ble_gap_config_adv_data(&mut self.advertisement_data));
+            }
+        }
+
+        self
+    }
+
+    /// Adds the devices that were recorded before the server started to the controller's filter accept list.
+    pub(crate) fn configure_accept_list(&self) {
+        for address in &self.accept_list {
+            Self::update_accept_list(true, *address);
+        }
+    }
+
+    pub(crate) fn on_accept_list_updated(
+        &self,
+        param: esp_ble_gap_cb_param_t_ble_update_whitelist_cmpl_evt_param,
+    ) {
+        if param.status == esp_bt_status_t_ESP_BT_STATUS_SUCCESS {
+            debug!("Filter accept list updated.");
+        } else {
+            warn!("Filter accept list update failed: {:?}.", param.status);
+        }
+    }
+
+    fn update_accept_list(add: bool, address: BleAddress) {
+        if add {
+            info!("Adding {} to the filter accept list.", address);
+        } else {
+            info!("Removing {} from the filter accept list.", address);
+        }
+
+        let address_type = match address.address_type() {
+            BleAddressType::Public => esp_ble_wl_addr_type_t_BLE_WL_ADDR_TYPE_PUBLIC,
+            BleAddressType::Random => esp_ble_wl_addr_type_t_BLE_WL_ADDR_TYPE_RANDOM,
+        };
+
+        let mut bytes = address.bytes();
+        let result = unsafe {
+            esp!(esp_ble_gap_update_whitelist(
+                add,
+                bytes.as_mut_ptr(),
+                address_type
+            ))
+        };
+
+        if let Err(error) = result {
+            warn!("Cannot update the filter accept list: {}.", error);
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::sync::{Arc, RwLock};
+
+use esp_idf_sys::*;
+use log::warn;
+
+use crate::{
+    gatt_server::{
+        custom_attributes::validate_cccd, Characteristic, Descriptor, GattServer, Profile, Service,
+    },
+    utilities::{AttributeOperation, BleUuid, Connection, SubscriptionMode},
+};
+
+/// A characteristic or descriptor of a [`Profile`], along with the attributes containing it.
+pub(crate) enum Attribute {
+    Characteristic {
+        service: Arc<RwLock<Service>>,
+        characteristic: Arc<RwLock<Characteristic>>,
+    },
+    Descriptor {
+        service: Arc<RwLock<Service>>,
+        characteristic: Arc<RwLock<Characteristic>>,
+        descriptor: Arc<RwLock<Descriptor>>,
+    },
+}
+
+impl Profile {
+    /// Finds the characteristic or descriptor registered at the given handle.
+    pub(crate) fn find_attribute(&self, handle: u16) -> Option<Attribute> {
+        for service in &self.services {
+            for characteristic in &service.read().unwrap().characteristics {
+                if characteristic.read().unwrap().attribute_handle == Some(handle) {
+                    return Some(Attribute::Characteristic {
+                        service: service.clone(),
+                        characteristic: characteristic.clone(),
+                    });
+                }
+
+                let descriptor = characteristic
+                    .read()
+                    .unwrap()
+                    .descriptors
+                    .iter()
+                    .find(|descriptor| descriptor.read().unwrap().attribute_handle == Some(handle))
+                    .cloned();
+
+                if let Some(descriptor) = descriptor {
+                    return Some(Attribute::Descriptor {
+                        service: service.clone(),
+                        characteristic: characteristic.clone(),
+                        descriptor,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl Attribute {
+    /// Returns whether the reads and writes of the attribute are answered by the application.
+    pub(crate) fn responds_by_app(&self) -> bool {
+        match self {
+            Self::Characteristic { characteristic, .. } => {
+                characteristic.read().unwrap().responds_by_app()
+            }
+            Self::Descriptor { descriptor, .. } => descriptor.read().unwrap().responds_by_app(),
+        }
+    }
+
+    /// Decides whether a client can access the attribute.
+    ///
+    /// Returns the ATT error to answer with if it cannot.
+    pub(crate) fn check_access(
+        &self,
+        server: &GattServer,
+        conn_id: u16,
+        handle: u16,
+        operation: AttributeOperation,
+    ) -> Result<(), esp_gatt_status_t> {
+        match self {
+            Self::Characteristic {
+                service,
+                characteristic,
+            } => {
+                let service = service.read().unwrap();
+                let characteristic = characteristic.read().unwrap();
+
+                server.check_access(
+                    conn_id,
+                    handle,
+                    characteristic.uuid,
+                    operation,
+                    &[
+                        characteristic.access_policy.as_ref(),
+                        service.access_policy.as_ref(),
+                    ],
+                    &[
+                        characteristic.authorization.as_ref(),
+                        service.authorization.as_ref(),
+                    ],
+                )
+            }
+            Self::Descriptor {
+                service,
+                characteristic,
+                descriptor,
+            } => {
+                let service = service.read().unwrap();
+                let characteristic = characteristic.read().unwrap();
+                let descriptor = descriptor.read().unwrap();
+
+                server.check_access(
+                    conn_id,
+                    handle,
+                    descriptor.uuid,
+                    operation,
+                    &[
+                        characteristic.access_policy.as_ref(),
+                        service.access_policy.as_ref(),
+                    ],
+                    &[
+                        descriptor.authorization.as_ref(),
+                        characteristic.authorization.as_ref(),
+                        service.authorization.as_ref(),
+                    ],
+                )
+            }
+        }
+    }
+
+    /// Returns the value to answer a read with.
+    pub(crate) fn read(&self, param: esp_ble_gatts_cb_param_t_gatts_read_evt_param) -> Vec<u8> {
+        match self {
+            Self::Characteristic { characteristic, .. } => {
+                characteristic.read().unwrap().read_value(param)
+            }
+            Self::Descriptor { descriptor, .. } => descriptor.read().unwrap().read_value(param),
+        }
+    }
+
+    /// Applies a write of a client, and calls the write callback of the attribute.
+    ///
+    /// Returns the ATT error to answer with if the value is rejected.
+    pub(crate) fn write(
+        &self,
+        value: Vec<u8>,
+        param: esp_ble_gatts_cb_param_t_gatts_write_evt_param,
+    ) -> Result<(), esp_gatt_status_t> {
+        match self {
+            Self::Characteristic { characteristic, .. } => {
+                characteristic
+                    .write()
+                    .unwrap()
+                    .store_written_value(&value)?;
+
+                // The lock is released before the callback runs, so that it can change the characteristic.
+                let write_callback = characteristic.read().unwrap().write_callback.clone();
+                if let Some(write_callback) = write_callback {
+                    write_callback(value, param);
+                }
+
+                Ok(())
+            }
+            Self::Descriptor {
+                characteristic,
+                descriptor,
+                ..
+            } => {
+                let is_cccd = descriptor.read().unwrap().uuid == BleUuid::Uuid16(0x2902);
+
+                // Reject reserved bits and unsupported modes.
+                if is_cccd {
+                    if let Err(status) =
+                        validate_cccd(characteristic.read().unwrap().properties, &value)
+                    {
+                        warn!("Rejected invalid CCCD value {:?}.", value);
+                        return Err(status);
+                    }
+                }
+
+                descriptor.write().unwrap().store_written_value(&value)?;
+
+                // Report the new subscription of the client, if it changed.
+                let subscriber = Connection::find(param.conn_id).filter(|_| is_cccd);
+                let current = value.first().and_then(|bits| {
+                    SubscriptionMode::from_cccd(bits & 0b0000_0001 != 0, bits & 0b0000_0010 != 0)
+                });
+
+                let write_callback = descriptor.read().unwrap().write_callback;
+                if let Some(write_callback) = write_callback {
+                    write_callback(value, param);
+                }
+
+                if let Some(connection) = subscriber {
+                    characteristic
+                        .read()
+                        .unwrap()
+                        .report_subscription(connection, current);
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Attribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Characteristic { characteristic, .. } => {
+                write!(f, "characteristic {}", characteristic.read().unwrap())
+            }
+            Self::Descriptor { descriptor, .. } => {
+                write!(f, "descriptor {}", descriptor.read().unwrap())
+            }
+        }
+    }
+}
+
+/// Answers a request with a status and a value.
+pub(crate) fn send_response(
+    gatts_if: esp_gatt_if_t,
+    conn_id: u16,
+    trans_id: u32,
+    status: esp_gatt_status_t,
+    handle: u16,
+    offset: u16,
+    value: &[u8],
+) {
+    // Extend the response to the maximum length.
+    let mut response = [0u8; 600];
+    let len = value.len().min(response.len());
+    response[..len].copy_from_slice(&value[..len]);
+
+    let mut esp_rsp = esp_gatt_rsp_t {
+        attr_value: esp_gatt_value_t {
+            auth_req: 0,
+            handle,
+            len: len as u16,
+            offset,
+            value: response,
+        },
+    };
+
+    unsafe {
+        esp_nofail!(esp_ble_gatts_send_response(
+            gatts_if,
+            conn_id,
+            trans_id,
+            status,
+            &mut esp_rsp
+        ));
+    }
+}
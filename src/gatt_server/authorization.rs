@@ -0,0 +1,160 @@
+use esp_idf_sys::*;
+use log::{debug, warn};
+
+use crate::{
+    gatt_server::GattServer,
+    utilities::{
+        AccessPolicy, AttributeOperation, AuthorizationCallback, AuthorizationRequest, BleUuid,
+        Connection,
+    },
+};
+
+impl GattServer {
+    /// Sets a callback that authorizes the access to every attribute of the server.
+    ///
+    /// The callback receives the context of the access, and returns whether it is allowed.
+    /// Denied accesses are answered with an "Insufficient Authorization" error,
+    /// and the read or write callback of the attribute is not called.
+    ///
+    /// Callbacks set on a [`Descriptor`], [`Characteristic`] or [`Service`] take precedence.
+    ///
+    /// # Notes
+    ///
+    /// The attributes with an authorization callback are answered by the application instead of the Bluetooth stack,
+    /// so that every read and write is checked, including long writes. Their values are kept in memory by this crate.
+    /// The callbacks must be set before the server is started, because the attributes are configured on registration.
+    ///
+    /// The callback will be called from the Bluetooth stack's context, so it must not block.
+    ///
+    /// [`Descriptor`]: crate::gatt_server::Descriptor
+    /// [`Characteristic`]: crate::gatt_server::Characteristic
+    /// [`Service`]: crate::gatt_server::Service
+    pub fn on_authorize<C: Fn(&AuthorizationRequest) -> bool + Send + Sync + 'static>(
+        &mut self,
+        callback: C,
+    ) -> &mut Self {
+        self.authorization = Some(AuthorizationCallback::new(callback));
+        self
+    }
+
+    /// Returns the access policies set on the services and characteristics, for auditing.
+    ///
+    /// Each policy is listed with the UUID of the attribute it is attached to.
+    #[must_use]
+    pub fn access_policies(&self) -> Vec<(BleUuid, AccessPolicy)> {
+        let mut policies = Vec::new();
+
+        for profile in &self.profiles {
+            for service in &profile.read().unwrap().services {
+                let service = service.read().unwrap();
+                if let Some(policy) = &service.access_policy {
+                    policies.push((service.uuid, policy.clone()));
+                }
+
+                for characteristic in &service.characteristics {
+                    let characteristic = characteristic.read().unwrap();
+                    if let Some(policy) = &characteristic.access_policy {
+                        policies.push((characteristic.uuid, policy.clone()));
+                    }
+                }
+            }
+        }
+
+        policies
+    }
+
+    /// Decides whether an attribute access is allowed.
+    ///
+    /// The most specific access policy is checked first, then the most specific authorization callback.
+    /// Accesses are allowed when neither is set.
+    ///
+    /// Returns the ATT error to answer with if the access is denied.
+    pub(crate) fn check_access(
+        &self,
+        conn_id: u16,
+        handle: u16,
+        attribute: BleUuid,
+        operation: AttributeOperation,
+        policies: &[Option<&AccessPolicy>],
+        callbacks: &[Option<&AuthorizationCallback>],
+    ) -> Result<(), esp_gatt_status_t> {
+        let policy = policies.iter().copied().flatten().next();
+        let callback = callbacks
+            .iter()
+            .copied()
+            .chain(std::iter::once(self.authorization.as_ref()))
+            .flatten()
+            .next();
+
+        if policy.is_none() && callback.is_none() {
+            return Ok(());
+        }
+
+        let Some(connection) = Connection::find(conn_id) else {
+            warn!(
+                "Unknown connection {}. Denying access to handle 0x{:04x}.",
+                conn_id, handle
+            );
+            return Err(esp_gatt_status_t_ESP_GATT_INSUF_AUTHORIZATION);
+        };
+
+        let request = AuthorizationRequest {
+            connection,
+            security: connection.security(),
+            attribute,
+            handle,
+            operation,
+        };
+
+        if let Some(policy) = policy {
+            if let Err(status) = policy.check(&request) {
+                debug!(
+                    "{:?} of handle 0x{:04x} by {} denied by access policy ({:#04x}).",
+                    operation, handle, connection, status
+                );
+                return Err(status);
+            }
+        }
+
+        if let Some(callback) = callback {
+            if !callback.allows(&request) {
+                debug!(
+                    "{:?} of handle 0x{:04x} by {} denied by authorization callback.",
+                    operation, handle, connection
+                );
+                return Err(esp_gatt_status_t_ESP_GATT_INSUF_AUTHORIZATION);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Answers a request with an error status.
+pub(crate) fn send_error_response(
+    gatts_if: esp_gatt_if_t,
+    conn_id: u16,
+    trans_id: u32,
+    handle: u16,
+    status: esp_gatt_status_t,
+) {
+    let mut esp_rsp = esp_gatt_rsp_t {
+        attr_value: esp_gatt_value_t {
+            auth_req: 0,
+            handle,
+            len: 0,
+            offset: 0,
+            value: [0u8; 600],
+        },
+    };
+
+    unsafe {
+        esp_nofail!(esp_ble_gatts_send_response(
+            gatts_if,
+            conn_id,
+            trans_id,
+            status,
+            &mut esp_rsp
+        ));
+    }
+}
@@ -0,0 +1,242 @@
+use std::ffi::{c_char, c_int};
+
+use esp_idf_sys::*;
+use log::{info, warn};
+
+use crate::{
+    gatt_server::GattServer,
+    utilities::{BleAddress, BondBlobError, BondExport, BondRecord},
+};
+
+// Key types of the Bluetooth stack's bond storage.
+const LE_KEY_PENC: u8 = 0x01;
+const LE_KEY_PID: u8 = 0x02;
+const LE_KEY_PCSRK: u8 = 0x04;
+const LE_KEY_LENC: u8 = 0x08;
+
+const BT_STATUS_SUCCESS: u32 = 0;
+
+/// A device address, as passed to the Bluetooth stack's storage functions: `bt_bdaddr_t`.
+#[repr(C)]
+struct BtBdAddr {
+    address: [u8; 6],
+}
+
+/// The identity key of a peer, as stored by the Bluetooth stack: `tBTM_LE_PID_KEYS`.
+///
+/// Unlike `esp_ble_pid_keys_t`, the address type is a single byte.
+#[repr(C)]
+struct PidKeys {
+    irk: [u8; 16],
+    addr_type: u8,
+    static_addr: [u8; 6],
+}
+
+// The other keys are stored as `tBTM_LE_PENC_KEYS`, `tBTM_LE_LENC_KEYS` and `tBTM_LE_PCSRK_KEYS`,
+// which the public key types mirror. The storage copies the keys by size, so a layout change
+// in a future ESP-IDF must fail the build rather than corrupt the bonds.
+const _: () = {
+    assert!(std::mem::size_of::<BtBdAddr>() == 6);
+    assert!(std::mem::size_of::<PidKeys>() == 23);
+    assert!(std::mem::size_of::<esp_ble_penc_keys_t>() == 28);
+    assert!(std::mem::size_of::<esp_ble_lenc_keys_t>() == 20);
+    assert!(std::mem::size_of::<esp_ble_pcsrk_keys_t>() == 24);
+};
+
+// The bond storage of the Bluetooth stack is not part of the public ESP-IDF API,
+// but it is the only way to restore keys that did not come from a pairing.
+// These functions only use the configuration of the Bluetooth stack, which they lock,
+// so they can be called from any thread.
+extern "C" {
+    fn btc_storage_add_ble_bonding_key(
+        remote_bd_addr: *mut BtBdAddr,
+        key: *mut c_char,
+        key_type: u8,
+        key_length: u8,
+    ) -> u32;
+    fn btc_storage_set_ble_dev_type(bd_addr: *mut BtBdAddr, flush: bool) -> u32;
+    fn btc_storage_set_remote_addr_type(
+        remote_bd_addr: *mut BtBdAddr,
+        addr_type: u8,
+        flush: bool,
+    ) -> u32;
+    fn btc_storage_get_remote_addr_type(
+        remote_bd_addr: *mut BtBdAddr,
+        addr_type: *mut c_int,
+    ) -> u32;
+}
+
+impl GattServer {
+    /// Exports the keys of the bonded peers into a versioned binary blob,
+    /// that can be restored with [`GattServer::import_bonds`], on this device or on a replacement.
+    ///
+    /// # Notes
+    ///
+    /// Only LE Secure Connections bonds are exported. The peers with a legacy bond are listed
+    /// in [`BondExport::skipped`].
+    ///
+    /// The blob contains the long term keys in clear: it must be stored and transferred securely.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are more than 255 bonds.
+    pub fn export_bonds() -> Result<BondExport, BondBlobError> {
+        let mut records = Vec::new();
+        let mut skipped = Vec::new();
+
+        for device in &Self::bond_device_list() {
+            match BondRecord::from_bond_device(device, Self::bond_address(device)) {
+                Some(record) => records.push(record),
+                None => {
+                    let identity = Self::bond_identity(device);
+                    warn!("Cannot export the legacy bond with {}.", identity);
+                    skipped.push(identity);
+                }
+            }
+        }
+
+        Ok(BondExport {
+            blob: BondRecord::to_blob(&records)?,
+            skipped,
+        })
+    }
+
+    /// Returns the keys of the bonded peers. Legacy bonds are skipped, see [`GattServer::export_bonds`].
+    #[must_use]
+    pub fn bond_records() -> Vec<BondRecord> {
+        Self::bond_device_list()
+            .iter()
+            .filter_map(|device| BondRecord::from_bond_device(device, Self::bond_address(device)))
+            .collect()
+    }
+
+    /// Returns the address a device bonded with, with the address type stored by the Bluetooth stack.
+    pub(crate) fn bond_address(device: &esp_ble_bond_dev_t) -> BleAddress {
+        let mut address = BtBdAddr {
+            address: device.bd_addr,
+        };
+        let mut address_type: c_int = 0;
+
+        if unsafe { btc_storage_get_remote_addr_type(&mut address, &mut address_type) }
+            != BT_STATUS_SUCCESS
+        {
+            warn!(
+                "Cannot read the address type of {:02X?}. Assuming a public address.",
+                device.bd_addr
+            );
+            return BleAddress::public(device.bd_addr);
+        }
+
+        BleAddress::from_esp(
+            device.bd_addr,
+            address_type
+                .try_into()
+                .unwrap_or(esp_ble_addr_type_t_BLE_ADDR_TYPE_PUBLIC),
+        )
+    }
+
+    /// Restores the bonds of a blob written by [`GattServer::export_bonds`].
+    ///
+    /// The bonds are written to the Bluetooth stack's persistent storage, replacing the existing bonds
+    /// with the same peers. The Bluetooth stack loads them on its next start: once the device restarts,
+    /// they are listed by [`GattServer::bonded_devices`] and added to the resolving list.
+    ///
+    /// Returns the number of restored bonds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server has not started, if the blob is malformed,
+    /// or if the Bluetooth stack cannot store a bond. Bonds stored before the failure are kept.
+    pub fn import_bonds(&mut self, blob: &[u8]) -> Result<usize, BondBlobError> {
+        if !self.started {
+            return Err(BondBlobError::NotStarted);
+        }
+
+        let records = BondRecord::from_blob(blob)?;
+
+        for record in &records {
+            Self::store_bond(record)?;
+        }
+
+        info!(
+            "Restored {} bonds. They will be loaded on the next start.",
+            records.len()
+        );
+
+        Ok(records.len())
+    }
+
+    /// Writes the keys of a bond to the Bluetooth stack's storage.
+    fn store_bond(record: &BondRecord) -> Result<(), BondBlobError> {
+        let mut address = BtBdAddr {
+            address: record.address.bytes(),
+        };
+        let security_level = record.security_level();
+
+        // Secure Connections bonds use the same long term key in both directions.
+        let mut penc_key = esp_ble_penc_keys_t {
+            ltk: record.ltk,
+            rand: [0; 8],
+            ediv: 0,
+            sec_level: security_level,
+            key_size: record.key_size,
+        };
+        let mut lenc_key = esp_ble_lenc_keys_t {
+            ltk: record.ltk,
+            div: 0,
+            key_size: record.key_size,
+            sec_level: security_level,
+        };
+
+        let mut success = unsafe {
+            btc_storage_set_remote_addr_type(
+                &mut address,
+                record.address.esp_address_type() as u8,
+                false,
+            ) == BT_STATUS_SUCCESS
+                && store_key(&mut address, &mut penc_key, LE_KEY_PENC)
+                && store_key(&mut address, &mut lenc_key, LE_KEY_LENC)
+        };
+
+        if let Some((identity, irk)) = record.identity {
+            let mut pid_key = PidKeys {
+                irk,
+                addr_type: identity.esp_address_type() as u8,
+                static_addr: identity.bytes(),
+            };
+            success &= unsafe { store_key(&mut address, &mut pid_key, LE_KEY_PID) };
+        }
+
+        if let Some((csrk, counter)) = record.csrk {
+            let mut pcsrk_key = esp_ble_pcsrk_keys_t {
+                counter,
+                csrk,
+                sec_level: security_level,
+            };
+            success &= unsafe { store_key(&mut address, &mut pcsrk_key, LE_KEY_PCSRK) };
+        }
+
+        success &= unsafe { btc_storage_set_ble_dev_type(&mut address, true) } == BT_STATUS_SUCCESS;
+
+        if success {
+            Ok(())
+        } else {
+            warn!("Cannot store the bond with {}.", record.address);
+            Err(BondBlobError::StorageFailed(record.address))
+        }
+    }
+}
+
+/// Writes a key of a bond to the Bluetooth stack's storage.
+///
+/// # Safety
+///
+/// The key must have the layout the Bluetooth stack expects for the key type.
+unsafe fn store_key<K>(address: &mut BtBdAddr, key: &mut K, key_type: u8) -> bool {
+    btc_storage_add_ble_bonding_key(
+        address,
+        (key as *mut K).cast(),
+        key_type,
+        std::mem::size_of::<K>() as u8,
+    ) == BT_STATUS_SUCCESS
+}
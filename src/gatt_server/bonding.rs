@@ -0,0 +1,150 @@
+use esp_idf_sys::*;
+use log::{info, warn};
+
+use crate::{
+    gatt_server::{custom_attributes::remove_cccd_values, storage::with_storage, GattServer},
+    utilities::BleAddress,
+};
+
+/// The storage key of the identity address of the administrator.
+const ADMINISTRATOR_KEY: &str = "admin";
+
+impl GattServer {
+    /// Returns the bonded peers.
+    ///
+    /// Peers that distributed their identity resolving key are listed with their identity address.
+    #[must_use]
+    pub fn bonded_devices() -> Vec<BleAddress> {
+        Self::bond_device_list()
+            .iter()
+            .map(Self::bond_identity)
+            .collect()
+    }
+
+    /// Returns whether a peer is bonded.
+    ///
+    /// The address can be the identity address of the peer, or any resolvable private address it uses.
+    #[must_use]
+    pub fn is_bonded(address: BleAddress) -> bool {
+        Self::resolve_address(address).is_some()
+    }
+
+    /// Removes the bond with a peer, and the subscriptions it left behind.
+    ///
+    /// The peer is disconnected if it is connected.
+    pub fn remove_bond(&mut self, address: BleAddress) -> &mut Self {
+        let Some(identity) = Self::resolve_address(address) else {
+            warn!("{} is not bonded.", address);
+            return self;
+        };
+
+        let devices = Self::bond_device_list();
+        let Some(device) = devices
+            .iter()
+            .find(|device| Self::bond_identity(device) == identity)
+        else {
+            warn!("{} is not bonded.", address);
+            return self;
+        };
+
+        Self::remove_bond_device(device);
+        self
+    }
+
+    /// Removes all the bonds, and the subscriptions they left behind.
+    ///
+    /// This is typically used on factory reset. All bonded peers are disconnected,
+    /// and the administrator is forgotten, see [`GattServer::administrator`].
+    pub fn clear_bonds(&mut self) -> &mut Self {
+        let devices = Self::bond_device_list();
+        info!("Removing {} bonds.", devices.len());
+
+        for device in &devices {
+            Self::remove_bond_device(device);
+        }
+
+        self.reset_administrator()
+    }
+
+    /// Returns the identity address of the administrator: the first peer that bonded with the device.
+    ///
+    /// The administrator is kept in the [`Storage`] of the server. It stays the administrator when its bond
+    /// is removed, so that no other peer is promoted in its place: it only has to bond again.
+    /// See [`AccessRule::FirstBonded`].
+    ///
+    /// [`Storage`]: crate::utilities::Storage
+    /// [`AccessRule::FirstBonded`]: crate::utilities::AccessRule::FirstBonded
+    #[must_use]
+    pub fn administrator() -> Option<BleAddress> {
+        let stored = match with_storage(|storage| storage.get(ADMINISTRATOR_KEY)) {
+            Ok(stored) => stored?,
+            Err(error) => {
+                warn!("Cannot read the administrator: {}.", error);
+                return None;
+            }
+        };
+
+        let stored: [u8; 7] = match stored.try_into() {
+            Ok(stored) => stored,
+            Err(stored) => {
+                warn!("Ignoring invalid administrator {:02X?}.", stored);
+                return None;
+            }
+        };
+
+        // The address is stored before its type.
+        let [address @ .., address_type] = stored;
+        Some(BleAddress::from_esp(address, address_type.into()))
+    }
+
+    /// Forgets the administrator, so that the next peer to bond becomes the administrator.
+    pub fn reset_administrator(&mut self) -> &mut Self {
+        if let Err(error) = with_storage(|storage| storage.remove(ADMINISTRATOR_KEY)) {
+            warn!("Cannot forget the administrator: {}.", error);
+        }
+
+        self
+    }
+
+    /// Makes a newly bonded peer the administrator, unless there is one already.
+    pub(crate) fn claim_administrator(identity: BleAddress) {
+        if Self::administrator().is_some() {
+            return;
+        }
+
+        let mut stored = identity.bytes().to_vec();
+        stored.push(identity.esp_address_type() as u8);
+
+        match with_storage(|storage| storage.set(ADMINISTRATOR_KEY, &stored)) {
+            Ok(()) => info!("{} is the administrator.", identity),
+            Err(error) => warn!("Cannot store the administrator {}: {}.", identity, error),
+        }
+    }
+
+    fn remove_bond_device(device: &esp_ble_bond_dev_t) {
+        let identity = Self::bond_identity(device);
+        info!("Removing bond with {}.", identity);
+
+        let mut address = device.bd_addr;
+        let result = unsafe { esp!(esp_ble_remove_bond_device(address.as_mut_ptr())) };
+
+        if let Err(error) = result {
+            warn!("Cannot remove bond with {}: {}.", identity, error);
+            return;
+        }
+
+        remove_cccd_values(identity);
+    }
+
+    /// Returns the identity address of a bonded device, or the address it bonded with.
+    pub(crate) fn bond_identity(device: &esp_ble_bond_dev_t) -> BleAddress {
+        if u32::from(device.bond_key.key_mask) & ESP_BLE_ID_KEY_MASK == 0 {
+            Self::bond_address(device)
+        } else {
+            BleAddress::from_esp(
+                device.bond_key.pid_key.static_addr,
+                device.bond_key.pid_key.addr_type,
+            )
+        }
+    }
+}
@@ -1,21 +1,50 @@
 use crate::{
-    gatt_server::descriptor::Descriptor,
+    gatt_server::{
+        custom_attributes::swap_reported_subscription,
+        descriptor::Descriptor,
+        indications::{enqueue_indication, PendingIndication},
+        notifications::{send_notification, PendingNotification},
+        persistence::{load_value, store_value, value_key},
+    },
     leaky_box_raw,
-    utilities::{AttributeControl, AttributePermissions, BleUuid, CharacteristicProperties},
+    utilities::{
+        AccessPolicy, AttributeControl, AttributePermissions, AuthorizationCallback,
+        AuthorizationRequest, BleUuid, CharacteristicProperties, Connection, DeliveryMode,
+        IndicationCallback, IndicationListener, IndicationOutcome, IndicationReceipt,
+        NotificationError, SubscriptionMode,
+    },
 };
 
 use esp_idf_sys::{
     esp_attr_control_t, esp_attr_value_t, esp_ble_gatts_add_char,
     esp_ble_gatts_cb_param_t_gatts_read_evt_param, esp_ble_gatts_cb_param_t_gatts_write_evt_param,
-    esp_ble_gatts_set_attr_value, esp_nofail,
+    esp_ble_gatts_set_attr_value, esp_gatt_if_t, esp_gatt_status_t,
+    esp_gatt_status_t_ESP_GATT_INVALID_ATTR_LEN, esp_nofail, ESP_GATT_RSP_BY_APP,
 };
 use log::{debug, warn};
 use std::{
+    collections::VecDeque,
     fmt::Formatter,
-    sync::{Arc, RwLock},
+    sync::{mpsc, Arc, RwLock},
 };
 
 type WriteCallback = dyn Fn(Vec<u8>, esp_ble_gatts_cb_param_t_gatts_write_evt_param) + Send + Sync;
+type SubscribeCallback = dyn Fn(Connection, SubscriptionMode) + Send + Sync;
+type UnsubscribeCallback = dyn Fn(Connection) + Send + Sync;
+
+/// What to do once the Bluetooth stack has stored a new value of a characteristic.
+#[derive(Debug)]
+pub(crate) enum ValueChange {
+    /// Notify and indicate the subscribed clients, and report the indications to the receipt, if any.
+    Broadcast {
+        /// Where the outcomes of the indications are reported.
+        receipt: Option<mpsc::Sender<(Connection, IndicationOutcome)>>,
+        /// The mode used for the clients that enabled both notifications and indications.
+        preferred: DeliveryMode,
+    },
+    /// Only store the value, because the clients were notified directly.
+    Silent,
+}
 
 /// Represents a GATT characteristic.
 #[derive(Clone)]
@@ -32,6 +61,8 @@ pub struct Characteristic {
     pub(crate) attribute_handle: Option<u16>,
     /// The handle of the containing service.
     service_handle: Option<u16>,
+    /// The interface of the profile this characteristic is registered in.
+    pub(crate) gatts_if: Option<esp_gatt_if_t>,
     /// The access permissions for this characteristic.
     permissions: AttributePermissions,
     /// The properties that are announced for this characteristic.
@@ -44,6 +75,26 @@ pub struct Characteristic {
     max_value_length: Option<u16>,
     /// A copy of the `control` property, in the `esp_attr_control_t` type, passed directly to the Bluetooth stack.
     internal_control: esp_attr_control_t,
+    /// The function deciding whether a client can access this characteristic.
+    pub(crate) authorization: Option<AuthorizationCallback>,
+    /// The rules deciding which clients can access this characteristic.
+    pub(crate) access_policy: Option<AccessPolicy>,
+    /// Whether every access must be authorized, so the Bluetooth stack leaves the reads and writes to the application.
+    pub(crate) guarded: bool,
+    /// The function to be called with the outcome of every indication.
+    pub(crate) indication_callback: Option<Arc<IndicationCallback>>,
+    /// The mode used for the clients that enabled both notifications and indications.
+    pub(crate) preferred_delivery: DeliveryMode,
+    /// The function to be called when a client subscribes, or changes its subscription.
+    subscribe_callback: Option<Arc<SubscribeCallback>>,
+    /// The function to be called when a client unsubscribes or disconnects.
+    unsubscribe_callback: Option<Arc<UnsubscribeCallback>>,
+    /// The value changes not yet confirmed by the Bluetooth stack, in order.
+    pub(crate) pending_changes: VecDeque<ValueChange>,
+    /// The storage key of the value, if it is persistent.
+    persistent_key: Option<String>,
+    /// The version of the default value, stored along with the persistent value.
+    default_version: u16,
 }
 
 impl Characteristic {
@@ -58,11 +109,22 @@ impl Characteristic {
             descriptors: Vec::new(),
             attribute_handle: None,
             service_handle: None,
+            gatts_if: None,
             permissions: AttributePermissions::default(),
             properties: CharacteristicProperties::default(),
             control: AttributeControl::AutomaticResponse(vec![0]),
             internal_control: AttributeControl::AutomaticResponse(vec![0]).into(),
             max_value_length: None,
+            authorization: None,
+            access_policy: None,
+            guarded: false,
+            indication_callback: None,
+            preferred_delivery: DeliveryMode::Indication,
+            subscribe_callback: None,
+            unsubscribe_callback: None,
+            pending_changes: VecDeque::new(),
+            persistent_key: None,
+            default_version: 0,
         }
     }
 
@@ -102,17 +164,20 @@ impl Characteristic {
     /// The callback will be called when a client reads the value of this characteristic.
     ///
     /// The callback must return a `Vec<u8>` containing the value to be put into the response to the read request.
+    /// The security of the link is available through [`LinkSecurity::of`], with the `conn_id` of the parameter.
     ///
     /// # Notes
     ///
     /// The callback will be called from the Bluetooth stack's context, so it must not block.
+    ///
+    /// [`LinkSecurity::of`]: crate::utilities::LinkSecurity::of
     pub fn on_read<
         C: Fn(esp_ble_gatts_cb_param_t_gatts_read_evt_param) -> Vec<u8> + Send + Sync + 'static,
     >(
         &mut self,
         callback: C,
     ) -> &mut Self {
-        if !self.properties.read || !self.permissions.read_access {
+        if !self.properties.read || self.permissions.read_access.is_none() {
             warn!(
                 "Characteristic {} does not have read permissions. Ignoring read callback.",
                 self
@@ -132,6 +197,9 @@ impl Characteristic {
     ///
     /// The callback receives a `Vec<u8>` with the written value.
     /// It is up to the library user to decode the data into a meaningful format.
+    /// The security of the link is available through [`LinkSecurity::of`], with the `conn_id` of the parameter.
+    ///
+    /// [`LinkSecurity::of`]: crate::utilities::LinkSecurity::of
     pub fn on_write(
         &mut self,
         callback: impl Fn(Vec<u8>, esp_ble_gatts_cb_param_t_gatts_write_evt_param)
@@ -140,7 +208,7 @@ impl Characteristic {
             + 'static,
     ) -> &mut Self {
         if !((self.properties.write || self.properties.write_without_response)
-            && self.permissions.write_access)
+            && self.permissions.write_access.is_some())
         {
             warn!(
                 "Characteristic {} does not have write permissions. Ignoring write callback.",
@@ -154,6 +222,169 @@ impl Characteristic {
         self
     }
 
+    /// Sets the [`AccessPolicy`] deciding which clients can read and write this [`Characteristic`].
+    pub fn access_policy(&mut self, policy: AccessPolicy) -> &mut Self {
+        self.access_policy = Some(policy);
+        self
+    }
+
+    /// Returns the [`AccessPolicy`] of this [`Characteristic`], if any.
+    #[must_use]
+    pub const fn get_access_policy(&self) -> Option<&AccessPolicy> {
+        self.access_policy.as_ref()
+    }
+
+    /// Sets a callback that authorizes the access to this [`Characteristic`].
+    ///
+    /// The callback receives the context of the access, and returns whether it is allowed.
+    /// Denied accesses are answered with an "Insufficient Authorization" error.
+    /// See [`GattServer::on_authorize`] for the details.
+    ///
+    /// [`GattServer::on_authorize`]: crate::gatt_server::GattServer::on_authorize
+    pub fn on_authorize<C: Fn(&AuthorizationRequest) -> bool + Send + Sync + 'static>(
+        &mut self,
+        callback: C,
+    ) -> &mut Self {
+        self.authorization = Some(AuthorizationCallback::new(callback));
+        self
+    }
+
+    /// Sets how value changes are pushed to the clients that enabled both notifications and indications.
+    ///
+    /// The other clients get the mode they enabled. The default is [`DeliveryMode::Indication`].
+    pub fn preferred_delivery(&mut self, mode: DeliveryMode) -> &mut Self {
+        self.preferred_delivery = mode;
+        self
+    }
+
+    /// Makes the value of this [`Characteristic`] persistent, in the [`Storage`] of the server at `key`.
+    ///
+    /// The stored value replaces the value set before registration, which becomes the default.
+    /// Values written by clients, including long writes once they are executed, or set with
+    /// [`Characteristic::set_value`] after registration, are stored once they have not changed for a second,
+    /// or when a client disconnects. See [`GattServer::flush_persistent_values`].
+    ///
+    /// The key is at most 14 characters long. Longer keys are ignored.
+    ///
+    /// [`Storage`]: crate::utilities::Storage
+    /// [`GattServer::flush_persistent_values`]: crate::gatt_server::GattServer::flush_persistent_values
+    pub fn persistent<S: AsRef<str>>(&mut self, key: S) -> &mut Self {
+        let key = key.as_ref();
+        if key.len() > 14 {
+            warn!(
+                "The persistent key {} is longer than 14 characters. Ignoring persistence.",
+                key
+            );
+            return self;
+        }
+
+        self.persistent_key = Some(value_key(key));
+        self
+    }
+
+    /// Sets the version of the default value of this persistent [`Characteristic`]. The default is 0.
+    ///
+    /// Stored values are ignored if they were stored under another version.
+    /// Change the version when the default value changes in a way that makes the stored values obsolete.
+    pub fn default_version(&mut self, version: u16) -> &mut Self {
+        self.default_version = version;
+        self
+    }
+
+    /// Sets a callback that is called when a client enables notifications or indications
+    /// in the CCCD of this [`Characteristic`], or changes which ones it enabled.
+    ///
+    /// A bonded client that reconnects with a stored subscription is reported again
+    /// once it is identified. This can be used to start sampling only while someone is listening.
+    ///
+    /// # Notes
+    ///
+    /// The callback will be called from the Bluetooth stack's context, so it must not block.
+    pub fn on_subscribe<C: Fn(Connection, SubscriptionMode) + Send + Sync + 'static>(
+        &mut self,
+        callback: C,
+    ) -> &mut Self {
+        self.subscribe_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sets a callback that is called when a subscribed client disables notifications and indications
+    /// in the CCCD of this [`Characteristic`], or disconnects.
+    ///
+    /// # Notes
+    ///
+    /// The callback will be called from the Bluetooth stack's context, so it must not block.
+    pub fn on_unsubscribe<C: Fn(Connection) + Send + Sync + 'static>(
+        &mut self,
+        callback: C,
+    ) -> &mut Self {
+        self.unsubscribe_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Returns the connected clients subscribed to this [`Characteristic`], with what they enabled.
+    #[must_use]
+    pub fn subscribers(&self) -> Vec<(Connection, SubscriptionMode)> {
+        Connection::connected()
+            .into_iter()
+            .filter_map(|connection| Some((connection, self.subscription_mode(&connection)?)))
+            .collect()
+    }
+
+    /// Returns what a client enabled in the CCCD of this [`Characteristic`], if anything.
+    pub(crate) fn subscription_mode(&self, connection: &Connection) -> Option<SubscriptionMode> {
+        let (notifications, indications) = self.cccd_status(connection)?;
+        SubscriptionMode::from_cccd(notifications, indications)
+    }
+
+    /// Reports the subscription of a client to the callbacks, if it changed since the last report.
+    pub(crate) fn report_subscription(
+        &self,
+        connection: Connection,
+        current: Option<SubscriptionMode>,
+    ) {
+        let Some(handle) = self.attribute_handle else {
+            return;
+        };
+
+        let previous = swap_reported_subscription(connection.id, handle, current);
+        if previous == current {
+            return;
+        }
+
+        match current {
+            Some(mode) => {
+                debug!("{} subscribed to {} ({:?}).", connection, self, mode);
+                if let Some(callback) = &self.subscribe_callback {
+                    callback(connection, mode);
+                }
+            }
+            None => {
+                debug!("{} unsubscribed from {}.", connection, self);
+                if let Some(callback) = &self.unsubscribe_callback {
+                    callback(connection);
+                }
+            }
+        }
+    }
+
+    /// Sets a callback that receives the outcome of every indication sent for this [`Characteristic`].
+    ///
+    /// Indications are sent one at a time on each connection: the next one waits until the client
+    /// confirms the previous one, for up to 30 seconds. After a timeout, the remaining indications
+    /// of the connection are reported as timed out, and no more are sent on it.
+    ///
+    /// # Notes
+    ///
+    /// The callback will be called from the Bluetooth stack's context, so it must not block.
+    pub fn on_indication<C: Fn(Connection, IndicationOutcome) + Send + Sync + 'static>(
+        &mut self,
+        callback: C,
+    ) -> &mut Self {
+        self.indication_callback = Some(Arc::new(callback));
+        self
+    }
+
     /// Creates a new "User description" descriptor for this characteristic
     /// that contains the name of the characteristic.
     pub fn show_name(&mut self) -> &mut Self {
@@ -185,8 +416,59 @@ impl Characteristic {
     /// the maximum size will be automatically set to the length of the latest value
     /// set before starting the server.
     pub fn set_value<T: Into<Vec<u8>>>(&mut self, value: T) -> &mut Self {
-        let value: Vec<u8> = value.into();
+        let preferred = self.preferred_delivery;
+        self.update_value(
+            value.into(),
+            ValueChange::Broadcast {
+                receipt: None,
+                preferred,
+            },
+        );
+        self
+    }
+
+    /// Sets the value of this [`Characteristic`] like [`Characteristic::set_value`],
+    /// using `mode` for the clients that enabled both notifications and indications.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is too long and the characteristic is already registered.
+    pub fn set_value_with_mode<T: Into<Vec<u8>>>(
+        &mut self,
+        value: T,
+        mode: DeliveryMode,
+    ) -> &mut Self {
+        self.update_value(
+            value.into(),
+            ValueChange::Broadcast {
+                receipt: None,
+                preferred: mode,
+            },
+        );
+        self
+    }
+
+    /// Sets the value of this [`Characteristic`] like [`Characteristic::set_value`],
+    /// and returns a receipt of the indications sent to the subscribed clients.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is too long and the characteristic is already registered.
+    pub fn set_value_with_receipt<T: Into<Vec<u8>>>(&mut self, value: T) -> IndicationReceipt {
+        let (sender, receiver) = mpsc::channel();
+        let preferred = self.preferred_delivery;
+        self.update_value(
+            value.into(),
+            ValueChange::Broadcast {
+                receipt: Some(sender),
+                preferred,
+            },
+        );
+        IndicationReceipt(receiver)
+    }
 
+    /// Panics if a value does not fit in this [`Characteristic`].
+    fn check_value_length(&self, value: &[u8]) {
         #[allow(clippy::manual_assert)]
         if let Some(max_value_length) = self.max_value_length {
             if value.len() > max_value_length as usize {
@@ -201,6 +483,10 @@ impl Characteristic {
                 self.internal_value.len()
             );
         }
+    }
+
+    fn update_value(&mut self, value: Vec<u8>, change: ValueChange) {
+        self.check_value_length(&value);
 
         self.internal_value = value;
         self.control = AttributeControl::AutomaticResponse(self.internal_value.clone());
@@ -212,6 +498,12 @@ impl Characteristic {
         );
 
         if let Some(handle) = self.attribute_handle {
+            if let Some(key) = &self.persistent_key {
+                store_value(key, self.default_version, &self.internal_value);
+            }
+
+            self.pending_changes.push_back(change);
+
             #[allow(clippy::cast_possible_truncation)]
             unsafe {
                 esp_nofail!(esp_ble_gatts_set_attr_value(
@@ -221,8 +513,194 @@ impl Characteristic {
                 ));
             }
         }
+    }
 
-        self
+    /// Sends a notification with `value` to every client that enabled notifications.
+    ///
+    /// Unlike [`Characteristic::set_value`], the notifications are sent right away,
+    /// and the value read by the clients is not changed. This suits high-rate streams.
+    /// Use [`Characteristic::notify_and_update`] to also change the value.
+    ///
+    /// Returns the number of notified clients. Clients that only enabled indications are skipped.
+    /// While a link is congested, its notifications wait in a queue, see [`FlowControl`].
+    /// With [`OverflowPolicy::Block`], the clients whose queue is full are skipped.
+    ///
+    /// [`FlowControl`]: crate::utilities::FlowControl
+    /// [`OverflowPolicy::Block`]: crate::utilities::OverflowPolicy::Block
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the characteristic is not registered or cannot notify.
+    pub fn notify<T: Into<Vec<u8>>>(&self, value: T) -> Result<usize, NotificationError> {
+        let (Some(gatts_if), Some(handle)) = (self.gatts_if, self.attribute_handle) else {
+            return Err(NotificationError::NotRegistered);
+        };
+
+        if !self.properties.notify {
+            return Err(NotificationError::NotSupported);
+        }
+
+        let value: Vec<u8> = value.into();
+        let mut notified = 0;
+
+        for connection in Connection::connected() {
+            if !matches!(self.cccd_status(&connection), Some((true, _))) {
+                continue;
+            }
+
+            let result = send_notification(
+                PendingNotification {
+                    conn_id: connection.id,
+                    gatts_if,
+                    handle,
+                    value: value.clone(),
+                },
+                true,
+            );
+
+            match result {
+                Ok(()) => notified += 1,
+                Err(error) => warn!("Failed to notify {} to {}: {}.", self, connection, error),
+            }
+        }
+
+        debug!("Notified {} to {} clients.", self, notified);
+        Ok(notified)
+    }
+
+    /// Sends a notification with `value` to every client that enabled notifications,
+    /// like [`Characteristic::notify`], and changes the value read by the clients.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the characteristic is not registered or cannot notify.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is too long, before any client is notified.
+    pub fn notify_and_update<T: Into<Vec<u8>>>(
+        &mut self,
+        value: T,
+    ) -> Result<usize, NotificationError> {
+        let value: Vec<u8> = value.into();
+
+        // Nothing is sent if the value does not fit.
+        self.check_value_length(&value);
+
+        let notified = self.notify(value.clone())?;
+        self.update_value(value, ValueChange::Silent);
+        Ok(notified)
+    }
+
+    /// Sends a notification with `value` to a single client.
+    ///
+    /// The value of this [`Characteristic`] is not changed, and the other subscribed clients are not notified.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the characteristic is not registered or cannot notify,
+    /// if the client has not enabled notifications, if the notification is dropped or refused by the [`FlowControl`],
+    /// or if the Bluetooth stack cannot send the value.
+    ///
+    /// [`FlowControl`]: crate::utilities::FlowControl
+    pub fn notify_to<T: Into<Vec<u8>>>(
+        &self,
+        connection: &Connection,
+        value: T,
+    ) -> Result<(), NotificationError> {
+        let (gatts_if, handle) = self.subscription(connection, false)?;
+
+        debug!("Notifying {} to {}.", self, connection);
+
+        send_notification(
+            PendingNotification {
+                conn_id: connection.id,
+                gatts_if,
+                handle,
+                value: value.into(),
+            },
+            true,
+        )
+    }
+
+    /// Sends an indication with `value` to a single client,
+    /// and returns a receipt of its confirmation.
+    ///
+    /// The value of this [`Characteristic`] is not changed, and the other subscribed clients are not indicated.
+    /// The indication is queued behind the outstanding indications of the client.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the characteristic is not registered or cannot indicate,
+    /// or if the client has not enabled indications.
+    pub fn indicate_to<T: Into<Vec<u8>>>(
+        &self,
+        connection: &Connection,
+        value: T,
+    ) -> Result<IndicationReceipt, NotificationError> {
+        let (gatts_if, handle) = self.subscription(connection, true)?;
+        let (sender, receiver) = mpsc::channel();
+
+        debug!("Indicating {} to {}.", self, connection);
+
+        enqueue_indication(PendingIndication {
+            connection: *connection,
+            gatts_if,
+            handle,
+            value: value.into(),
+            listener: IndicationListener {
+                callback: self.indication_callback.clone(),
+                receipt: Some(sender),
+            },
+        });
+
+        Ok(IndicationReceipt(receiver))
+    }
+
+    /// Checks that a client enabled notifications or indications of this [`Characteristic`],
+    /// and returns the interface and the handle to send them with.
+    fn subscription(
+        &self,
+        connection: &Connection,
+        indication: bool,
+    ) -> Result<(esp_gatt_if_t, u16), NotificationError> {
+        let (Some(gatts_if), Some(handle)) = (self.gatts_if, self.attribute_handle) else {
+            return Err(NotificationError::NotRegistered);
+        };
+
+        let supported = if indication {
+            self.properties.indicate
+        } else {
+            self.properties.notify
+        };
+        if !supported {
+            return Err(NotificationError::NotSupported);
+        }
+
+        match self.cccd_status(connection) {
+            Some((_, true)) if indication => Ok((gatts_if, handle)),
+            Some((true, _)) if !indication => Ok((gatts_if, handle)),
+            _ => Err(NotificationError::NotSubscribed),
+        }
+    }
+
+    /// Returns whether a client enabled notifications and indications of this [`Characteristic`].
+    pub(crate) fn cccd_status(&self, connection: &Connection) -> Option<(bool, bool)> {
+        let handle = self
+            .descriptors
+            .iter()
+            .find(|descriptor| descriptor.read().unwrap().uuid == BleUuid::Uuid16(0x2902))?
+            .read()
+            .unwrap()
+            .attribute_handle?;
+
+        // Get the current status of the CCCD via a fake read operation.
+        self.get_cccd_status(esp_ble_gatts_cb_param_t_gatts_read_evt_param {
+            bda: connection.remote_bda,
+            conn_id: connection.id,
+            handle,
+            ..Default::default()
+        })
     }
 
     /// Returns a reference to the built [`Characteristic`] behind an `Arc` and an `RwLock`.
@@ -234,6 +712,13 @@ impl Characteristic {
         Arc::new(RwLock::new(self.clone()))
     }
 
+    /// Returns the number of attribute handles this [`Characteristic`] takes once registered.
+    pub(crate) fn handle_count(&self) -> usize {
+        // The declaration, the value, the descriptors, and the CCCD added on registration.
+        let cccd = usize::from(self.properties.notify || self.properties.indicate);
+        2 + self.descriptors.len() + cccd
+    }
+
     /// Registers the [`Characteristic`] at the given service handle.
     pub(crate) fn register_self(&mut self, service_handle: u16) {
         debug!(
@@ -241,6 +726,7 @@ impl Characteristic {
             self, service_handle
         );
         self.service_handle = Some(service_handle);
+        self.load_persistent_value();
 
         #[allow(clippy::manual_assert)]
         if let AttributeControl::AutomaticResponse(_) = self.control {
@@ -254,6 +740,13 @@ impl Characteristic {
             self.descriptor(&Descriptor::cccd().build());
         }
 
+        // The value is kept by the application, and served from `internal_value`.
+        if self.responds_by_app() {
+            self.internal_control = esp_attr_control_t {
+                auto_rsp: ESP_GATT_RSP_BY_APP as u8,
+            };
+        }
+
         #[allow(clippy::cast_possible_truncation)]
         unsafe {
             esp_nofail!(esp_ble_gatts_add_char(
@@ -273,6 +766,97 @@ impl Characteristic {
         }
     }
 
+    /// Returns whether the reads and writes of this [`Characteristic`] are answered by the application.
+    ///
+    /// Persistent values are answered by the application, so that long writes are stored once executed.
+    pub(crate) fn responds_by_app(&self) -> bool {
+        self.guarded
+            || self.persistent_key.is_some()
+            || matches!(self.control, AttributeControl::ResponseByApp(_))
+    }
+
+    /// Returns the value to answer a read with.
+    pub(crate) fn read_value(
+        &self,
+        param: esp_ble_gatts_cb_param_t_gatts_read_evt_param,
+    ) -> Vec<u8> {
+        match &self.control {
+            AttributeControl::ResponseByApp(callback) => callback(param),
+            AttributeControl::AutomaticResponse(_) => self
+                .internal_value
+                .get(usize::from(param.offset)..)
+                .unwrap_or_default()
+                .to_vec(),
+        }
+    }
+
+    /// Keeps a value written by a client, when the Bluetooth stack leaves the writes to the application.
+    ///
+    /// Returns the ATT error to answer with if the value is too long.
+    pub(crate) fn store_written_value(&mut self, value: &[u8]) -> Result<(), esp_gatt_status_t> {
+        if !self.responds_by_app() {
+            return Ok(());
+        }
+
+        if let AttributeControl::AutomaticResponse(_) = self.control {
+            let max_value_length = self
+                .max_value_length
+                .map_or(self.internal_value.len(), usize::from);
+
+            if value.len() > max_value_length {
+                return Err(esp_gatt_status_t_ESP_GATT_INVALID_ATTR_LEN);
+            }
+
+            self.internal_value = value.to_vec();
+            self.control = AttributeControl::AutomaticResponse(self.internal_value.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the default value with the stored one, and stores the values written by the clients.
+    fn load_persistent_value(&mut self) {
+        let Some(key) = self.persistent_key.clone() else {
+            return;
+        };
+
+        if let Some(value) = load_value(&key, self.default_version) {
+            let max_value_length = self
+                .max_value_length
+                .map_or(self.internal_value.len(), usize::from);
+
+            if value.len() > max_value_length {
+                warn!(
+                    "Stored value of {} is too long. Using the default value.",
+                    self
+                );
+            } else {
+                // Keep room for values as long as the default one.
+                self.max_value_length = Some(max_value_length as u16);
+                self.internal_value = value;
+                if let AttributeControl::AutomaticResponse(_) = self.control {
+                    self.control = AttributeControl::AutomaticResponse(self.internal_value.clone());
+                    self.internal_control = self.control.clone().into();
+                }
+            }
+        }
+
+        let version = self.default_version;
+        let callback = self.write_callback.take();
+        self.write_callback = Some(Arc::new(
+            move |value: Vec<u8>, param: esp_ble_gatts_cb_param_t_gatts_write_evt_param| {
+                // Long writes are stored once executed, as a single write.
+                if !param.is_prep {
+                    store_value(&key, version, &value);
+                }
+
+                if let Some(callback) = &callback {
+                    callback(value, param);
+                }
+            },
+        ));
+    }
+
     /// Registers the descriptors of this [`Characteristic`].
     ///
     /// This function should be called on the event of the characteristic being registered.
@@ -288,12 +872,11 @@ impl Characteristic {
     pub(crate) fn register_descriptors(&mut self) {
         debug!("Registering {}'s descriptors.", &self);
         self.descriptors.iter_mut().for_each(|descriptor| {
-            descriptor
-                .write()
-                .unwrap()
-                .register_self(self.service_handle.expect(
-                    "Cannot register a descriptor to a characteristic without a service handle.",
-                ));
+            let mut descriptor = descriptor.write().unwrap();
+            descriptor.guarded = self.guarded || descriptor.authorization.is_some();
+            descriptor.register_self(self.service_handle.expect(
+                "Cannot register a descriptor to a characteristic without a service handle.",
+            ));
         });
     }
 
@@ -343,12 +926,21 @@ impl std::fmt::Debug for Characteristic {
             .field("descriptors", &self.descriptors)
             .field("attribute_handle", &self.attribute_handle)
             .field("service_handle", &self.service_handle)
+            .field("gatts_if", &self.gatts_if)
             .field("permissions", &self.permissions)
             .field("properties", &self.properties)
             .field("control", &self.control)
             .field("internal_value", &self.internal_value)
             .field("max_value_length", &self.max_value_length)
             .field("internal_control", &self.internal_control)
+            .field("authorization", &self.authorization.is_some())
+            .field("access_policy", &self.access_policy)
+            .field("guarded", &self.guarded)
+            .field("indication_callback", &self.indication_callback.is_some())
+            .field("preferred_delivery", &self.preferred_delivery)
+            .field("subscribe_callback", &self.subscribe_callback.is_some())
+            .field("unsubscribe_callback", &self.unsubscribe_callback.is_some())
+            .field("pending_changes", &self.pending_changes)
             .finish()
     }
 }
@@ -0,0 +1,68 @@
+use esp_idf_sys::*;
+use log::{debug, info, warn};
+
+use crate::{gatt_server::GattServer, utilities::Connection};
+
+impl GattServer {
+    /// Sets the maximum number of clients that can be connected at the same time.
+    ///
+    /// The server keeps advertising after each connection until the limit is reached.
+    /// The default is one client. The limit cannot exceed the number of connections
+    /// that Bluedroid is configured for, `CONFIG_BT_ACL_CONNECTIONS`.
+    pub fn max_connections(&mut self, limit: usize) -> &mut Self {
+        let supported = CONFIG_BT_ACL_CONNECTIONS as usize;
+
+        if limit == 0 {
+            warn!("The connection limit must be at least one. Ignoring connection limit.");
+            return self;
+        }
+
+        if limit > supported {
+            warn!(
+                "Bluedroid supports at most {} connections. Limiting to {}.",
+                supported, supported
+            );
+        }
+
+        self.max_connections = limit.min(supported);
+        self
+    }
+
+    /// Returns the currently connected clients.
+    #[must_use]
+    pub fn connections(&self) -> Vec<Connection> {
+        Connection::connected()
+    }
+
+    /// Starts advertising with the normal parameters.
+    ///
+    /// Connectable advertisements are not started when the connection limit is reached.
+    pub(crate) fn start_advertising(&mut self) {
+        if self.advertising {
+            debug!("Already advertising.");
+            return;
+        }
+
+        let connectable = self.advertisement_parameters.adv_type
+            != esp_ble_adv_type_t_ADV_TYPE_NONCONN_IND
+            && self.advertisement_parameters.adv_type != esp_ble_adv_type_t_ADV_TYPE_SCAN_IND;
+
+        if connectable && Connection::count() >= self.max_connections {
+            info!(
+                "Connection limit of {} reached. Not advertising.",
+                self.max_connections
+            );
+            return;
+        }
+
+        let result = unsafe {
+            esp!(esp_ble_gap_start_advertising(
+                &mut self.advertisement_parameters
+            ))
+        };
+
+        if let Err(error) = result {
+            warn!("Cannot start advertising: {}.", error);
+        }
+    }
+}
@@ -1,24 +1,47 @@
 use crate::{
-    gatt_server::Descriptor,
-    utilities::{AttributePermissions, BleUuid},
+    gatt_server::{persistence::fnv1a, storage::with_storage, Descriptor, GattServer, Service},
+    utilities::{
+        AttributePermissions, BleAddress, BleUuid, CharacteristicProperties, Storage,
+        SubscriptionMode,
+    },
 };
 
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+};
 
-use esp_idf_svc::nvs::{EspDefaultNvs, EspDefaultNvsPartition};
+use esp_idf_sys::{
+    esp_ble_gatts_cb_param_t_gatts_read_evt_param, esp_ble_gatts_cb_param_t_gatts_write_evt_param,
+    esp_gatt_status_t, esp_gatt_status_t_ESP_GATT_CCC_CFG_ERR,
+    esp_gatt_status_t_ESP_GATT_INVALID_ATTR_LEN, EspError,
+};
 use lazy_static::lazy_static;
-use log::debug;
+use log::{debug, warn};
+
+/// The storage key of the identity addresses of the peers with stored CCCD values.
+const CCCD_OWNERS_KEY: &str = "cccd_owners";
+
+/// The length of the storage key of a CCCD value.
+const CCCD_KEY_LEN: usize = 15;
 
 lazy_static! {
-    static ref STORAGE: Arc<Mutex<EspDefaultNvs>> = Arc::new(Mutex::new(
-        EspDefaultNvs::new(
-            EspDefaultNvsPartition::take()
-                .expect("Cannot initialise the default NVS. Did you declare an NVS partition?"),
-            "ble",
-            true
-        )
-        .expect("Cannot create a new NVS storage. Did you declare an NVS partition?")
-    ));
+    /// The path of every registered CCCD, by attribute handle.
+    ///
+    /// The path names the service and the characteristic, so that it survives a change of the attribute table.
+    static ref CCCD_PATHS: Mutex<HashMap<u16, String>> = Mutex::new(HashMap::new());
+
+    /// The CCCD values of the unbonded clients, by address and path.
+    ///
+    /// They are only kept for the duration of the connection.
+    static ref VOLATILE_CCCDS: Mutex<HashMap<([u8; 6], String), Vec<u8>>> = Mutex::new(HashMap::new());
+
+    /// The subscriptions reported to the callbacks, by connection identifier and CCCD handle.
+    ///
+    /// Reports are made against this record, so that every unsubscription follows a subscription,
+    /// including the ones a bonded client kept in its stored CCCD values.
+    static ref REPORTED_SUBSCRIPTIONS: Mutex<HashMap<(u16, u16), SubscriptionMode>> =
+        Mutex::new(HashMap::new());
 }
 
 impl Descriptor {
@@ -39,66 +62,358 @@ impl Descriptor {
 
     /// Creates a CCCD.
     ///
-    /// The contents of the CCCD are stored in NVS and persisted across reboots.
+    /// The contents of the CCCD are stored per client and per characteristic.
+    /// They are persisted in the [`Storage`] of the server for bonded clients,
+    /// and kept in memory until disconnection for the others.
     ///
-    /// # Panics
-    ///
-    /// Panics if the NVS is not configured.
+    /// [`Storage`]: crate::utilities::Storage
     #[must_use]
     pub fn cccd() -> Self {
         Self::new(BleUuid::from_uuid16(0x2902))
             .name("Client Characteristic Configuration")
             .permissions(AttributePermissions::new().read().write())
-            .on_read(
-                |param: esp_idf_sys::esp_ble_gatts_cb_param_t_gatts_read_evt_param| {
-                    let storage = STORAGE.lock().unwrap();
-
-                    // Get the descriptor handle.
-
-                    // TODO: Find the characteristic that contains the handle.
-                    // WARNING: Using the handle is incredibly stupid as the NVS is not erased across flashes.
-
-                    // Create a key from the connection address.
-                    let key = format!(
-                        "{:02X}{:02X}{:02X}{:02X}-{:04X}",
-                        /* param.bda[1], */ param.bda[2],
-                        param.bda[3],
-                        param.bda[4],
-                        param.bda[5],
-                        param.handle
-                    );
-
-                    // Prepare buffer and read correct CCCD value from non-volatile storage.
-                    let mut buf: [u8; 2] = [0; 2];
-                    if let Some(value) = storage.get_raw(&key, &mut buf).unwrap() {
-                        debug!("Read CCCD value: {:?} for key {}.", value, key);
-                        value.to_vec()
-                    } else {
-                        debug!("No CCCD value found for key {}.", key);
-                        vec![0, 0]
-                    }
-                },
-            )
-            .on_write(|value, param| {
-                let mut storage = STORAGE.lock().unwrap();
-
-                // Create a key from the connection address.
-                let key = format!(
-                    "{:02X}{:02X}{:02X}{:02X}-{:04X}",
-                    /* param.bda[1], */ param.bda[2],
-                    param.bda[3],
-                    param.bda[4],
-                    param.bda[5],
-                    param.handle
-                );
-
-                debug!("Write CCCD value: {:?} at key {}", value, key);
-
-                // Write CCCD value to non-volatile storage.
-                storage
-                    .set_raw(&key, &value)
-                    .expect("Cannot put raw value to the NVS. Did you declare an NVS partition?");
-            })
+            .on_read(read_cccd)
+            .on_write(write_cccd)
             .clone()
     }
 }
+
+impl Service {
+    /// Remembers the path of a CCCD of this service, once it is registered at `handle`.
+    pub(crate) fn register_cccd(&self, descriptor: &Arc<RwLock<Descriptor>>, handle: u16) {
+        if descriptor.read().unwrap().uuid != BleUuid::Uuid16(0x2902) {
+            return;
+        }
+
+        let Some(characteristic) = self.characteristics.iter().find(|characteristic| {
+            characteristic
+                .read()
+                .unwrap()
+                .descriptors
+                .iter()
+                .any(|candidate| Arc::ptr_eq(candidate, descriptor))
+        }) else {
+            warn!(
+                "Cannot find the characteristic of the CCCD at handle 0x{:04x}.",
+                handle
+            );
+            return;
+        };
+
+        let path = format!("{}/{}", self.uuid, characteristic.read().unwrap().uuid);
+        CCCD_PATHS.lock().unwrap().insert(handle, path);
+    }
+}
+
+/// Where the CCCD values of a client are kept.
+enum CccdStore {
+    /// In the storage, by identity address, for a bonded client.
+    Persistent(BleAddress),
+    /// In memory, by connection address, for an unbonded client.
+    Volatile([u8; 6]),
+}
+
+impl CccdStore {
+    fn of(address: [u8; 6]) -> Self {
+        GattServer::resolve_address(BleAddress::public(address))
+            .map_or(Self::Volatile(address), Self::Persistent)
+    }
+}
+
+fn read_cccd(param: esp_ble_gatts_cb_param_t_gatts_read_evt_param) -> Vec<u8> {
+    let Some(path) = CCCD_PATHS.lock().unwrap().get(&param.handle).cloned() else {
+        warn!("Unknown CCCD at handle 0x{:04x}.", param.handle);
+        return vec![0, 0];
+    };
+
+    let value = match CccdStore::of(param.bda) {
+        CccdStore::Persistent(identity) => {
+            let key = cccd_key(identity, &path);
+            with_storage(|storage| storage.get(&key)).unwrap_or_else(|error| {
+                warn!("Cannot read CCCD value at key {}: {}.", key, error);
+                None
+            })
+        }
+        CccdStore::Volatile(address) => VOLATILE_CCCDS
+            .lock()
+            .unwrap()
+            .get(&(address, path.clone()))
+            .cloned(),
+    };
+
+    debug!("Read CCCD value {:?} of {}.", value, path);
+    value.unwrap_or_else(|| vec![0, 0])
+}
+
+fn write_cccd(value: Vec<u8>, param: esp_ble_gatts_cb_param_t_gatts_write_evt_param) {
+    let Some(path) = CCCD_PATHS.lock().unwrap().get(&param.handle).cloned() else {
+        warn!("Unknown CCCD at handle 0x{:04x}.", param.handle);
+        return;
+    };
+
+    debug!("Write CCCD value {:?} of {}.", value, path);
+
+    match CccdStore::of(param.bda) {
+        CccdStore::Persistent(identity) => {
+            if let Err(error) =
+                with_storage(|storage| store_cccd_value(storage, identity, &path, &value))
+            {
+                warn!("Cannot write CCCD value of {}: {}.", path, error);
+            }
+        }
+        CccdStore::Volatile(address) => {
+            VOLATILE_CCCDS
+                .lock()
+                .unwrap()
+                .insert((address, path), value);
+        }
+    }
+}
+
+/// Checks a value written to the CCCD of a characteristic with the given properties.
+///
+/// Returns the ATT error to answer with if the value is invalid.
+pub(crate) fn validate_cccd(
+    properties: CharacteristicProperties,
+    value: &[u8],
+) -> Result<(), esp_gatt_status_t> {
+    let [low, high] = value else {
+        return Err(esp_gatt_status_t_ESP_GATT_INVALID_ATTR_LEN);
+    };
+
+    let notifications = low & 0b0000_0001 != 0;
+    let indications = low & 0b0000_0010 != 0;
+
+    // The other bits are reserved, and the enabled modes must be supported.
+    if low & !0b0000_0011 != 0
+        || *high != 0
+        || (notifications && !properties.notify)
+        || (indications && !properties.indicate)
+    {
+        return Err(esp_gatt_status_t_ESP_GATT_CCC_CFG_ERR);
+    }
+
+    Ok(())
+}
+
+/// Creates the NVS key of a CCCD value from the identity address of the peer and the path of the CCCD.
+fn cccd_key(identity: BleAddress, path: &str) -> String {
+    // NVS keys are limited to 15 characters, so the address and the path are hashed together.
+    let hash = fnv1a(identity.bytes().iter().chain(path.as_bytes()));
+
+    format!("c{:014x}", hash >> 8)
+}
+
+/// Creates the storage key of the list of CCCD values stored for a bonded peer.
+fn cccd_index_key(identity: BleAddress) -> String {
+    let [a, b, c, d, e, f] = identity.bytes();
+    format!("i{a:02x}{b:02x}{c:02x}{d:02x}{e:02x}{f:02x}")
+}
+
+/// Stores a CCCD value of a bonded peer, and lists it with the peer,
+/// so that it can be removed with the bond whatever the registered CCCDs are.
+fn store_cccd_value(
+    storage: &mut dyn Storage,
+    identity: BleAddress,
+    path: &str,
+    value: &[u8],
+) -> Result<(), EspError> {
+    let key = cccd_key(identity, path);
+    storage.set(&key, value)?;
+
+    // The index holds the keys one after the other, as they all have the same length.
+    let index_key = cccd_index_key(identity);
+    let mut index = storage.get(&index_key)?.unwrap_or_default();
+    if !index
+        .chunks(CCCD_KEY_LEN)
+        .any(|stored| stored == key.as_bytes())
+    {
+        index.extend_from_slice(key.as_bytes());
+        storage.set(&index_key, &index)?;
+    }
+
+    let mut owners = storage.get(CCCD_OWNERS_KEY)?.unwrap_or_default();
+    if !owners.chunks(6).any(|owner| owner == identity.bytes()) {
+        owners.extend_from_slice(&identity.bytes());
+        storage.set(CCCD_OWNERS_KEY, &owners)?;
+    }
+
+    Ok(())
+}
+
+/// Moves the CCCD values of a client that just bonded to the storage.
+pub(crate) fn persist_cccd_values(address: [u8; 6], identity: BleAddress) {
+    let mut volatile = VOLATILE_CCCDS.lock().unwrap();
+    let paths: Vec<String> = volatile
+        .keys()
+        .filter(|(candidate, _)| *candidate == address)
+        .map(|(_, path)| path.clone())
+        .collect();
+
+    with_storage(|storage| {
+        for path in paths {
+            if let Some(value) = volatile.remove(&(address, path.clone())) {
+                if let Err(error) = store_cccd_value(storage, identity, &path, &value) {
+                    warn!("Cannot persist CCCD value of {}: {}.", path, error);
+                }
+            }
+        }
+    });
+}
+
+/// Records the subscription of a client reported to the callbacks, and returns the previous one.
+pub(crate) fn swap_reported_subscription(
+    conn_id: u16,
+    handle: u16,
+    current: Option<SubscriptionMode>,
+) -> Option<SubscriptionMode> {
+    let mut reported = REPORTED_SUBSCRIPTIONS.lock().unwrap();
+    match current {
+        Some(mode) => reported.insert((conn_id, handle), mode),
+        None => reported.remove(&(conn_id, handle)),
+    }
+}
+
+/// Forgets the CCCD values of an unbonded client, once it disconnected.
+pub(crate) fn forget_cccd_values(address: [u8; 6]) {
+    VOLATILE_CCCDS
+        .lock()
+        .unwrap()
+        .retain(|(candidate, _), _| *candidate != address);
+}
+
+/// Removes the stored CCCD values of a bonded peer.
+pub(crate) fn remove_cccd_values(identity: BleAddress) {
+    if let Err(error) = with_storage(|storage| remove_stored_cccd_values(storage, identity)) {
+        warn!("Cannot remove the CCCD values of {}: {}.", identity, error);
+    }
+}
+
+/// Removes the stored CCCD values of the peers that are not bonded anymore,
+/// such as the bonds that the Bluetooth stack dropped to make room for new ones.
+pub(crate) fn remove_orphaned_cccd_values() {
+    let bonded: Vec<[u8; 6]> = GattServer::bonded_devices()
+        .iter()
+        .map(BleAddress::bytes)
+        .collect();
+
+    let result = with_storage(|storage| {
+        let owners = storage.get(CCCD_OWNERS_KEY)?.unwrap_or_default();
+
+        for owner in owners.chunks_exact(6) {
+            let Ok(owner) = <[u8; 6]>::try_from(owner) else {
+                continue;
+            };
+
+            if !bonded.contains(&owner) {
+                remove_stored_cccd_values(storage, BleAddress::public(owner))?;
+            }
+        }
+
+        Ok::<(), EspError>(())
+    });
+
+    if let Err(error) = result {
+        warn!(
+            "Cannot remove the CCCD values of the removed bonds: {}.",
+            error
+        );
+    }
+}
+
+/// Removes the CCCD values listed with a peer, and the peer from the list of owners.
+fn remove_stored_cccd_values(
+    storage: &mut dyn Storage,
+    identity: BleAddress,
+) -> Result<(), EspError> {
+    let index_key = cccd_index_key(identity);
+    let index = storage.get(&index_key)?.unwrap_or_default();
+
+    for key in index.chunks(CCCD_KEY_LEN) {
+        let key = String::from_utf8_lossy(key);
+        if storage.remove(&key)? {
+            debug!("Removed CCCD value at key {}.", key);
+        }
+    }
+    storage.remove(&index_key)?;
+
+    let owners = storage.get(CCCD_OWNERS_KEY)?.unwrap_or_default();
+    let remaining: Vec<u8> = owners
+        .chunks(6)
+        .filter(|owner| *owner != identity.bytes())
+        .flatten()
+        .copied()
+        .collect();
+    if remaining.len() != owners.len() {
+        storage.set(CCCD_OWNERS_KEY, &remaining)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_supported_modes() {
+        let both = CharacteristicProperties::new().notify().indicate();
+
+        assert_eq!(validate_cccd(both, &[0x00, 0x00]), Ok(()));
+        assert_eq!(validate_cccd(both, &[0x01, 0x00]), Ok(()));
+        assert_eq!(validate_cccd(both, &[0x02, 0x00]), Ok(()));
+        assert_eq!(validate_cccd(both, &[0x03, 0x00]), Ok(()));
+    }
+
+    #[test]
+    fn rejects_wrong_length() {
+        let notify = CharacteristicProperties::new().notify();
+
+        for value in [&[][..], &[0x01], &[0x01, 0x00, 0x00]] {
+            assert_eq!(
+                validate_cccd(notify, value),
+                Err(esp_gatt_status_t_ESP_GATT_INVALID_ATTR_LEN)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_reserved_bits() {
+        let both = CharacteristicProperties::new().notify().indicate();
+
+        for value in [[0x04, 0x00], [0x81, 0x00], [0x01, 0x01], [0x00, 0x80]] {
+            assert_eq!(
+                validate_cccd(both, &value),
+                Err(esp_gatt_status_t_ESP_GATT_CCC_CFG_ERR)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_modes() {
+        let notify = CharacteristicProperties::new().notify();
+        let indicate = CharacteristicProperties::new().indicate();
+
+        assert_eq!(
+            validate_cccd(notify, &[0x02, 0x00]),
+            Err(esp_gatt_status_t_ESP_GATT_CCC_CFG_ERR)
+        );
+        assert_eq!(
+            validate_cccd(indicate, &[0x01, 0x00]),
+            Err(esp_gatt_status_t_ESP_GATT_CCC_CFG_ERR)
+        );
+        assert_eq!(
+            validate_cccd(CharacteristicProperties::new(), &[0x00, 0x00]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn cccd_keys_fit_in_nvs() {
+        let identity = BleAddress::public([0x11, 0x22, 0x33, 0x44, 0x55, 0x66]);
+
+        assert_eq!(cccd_key(identity, "service/characteristic").len(), 15);
+        assert_eq!(cccd_index_key(identity), "i112233445566");
+        assert_ne!(cccd_key(identity, "a"), cccd_key(identity, "b"));
+    }
+}
@@ -2,13 +2,17 @@ use std::sync::{Arc, RwLock};
 
 use crate::{
     leaky_box_raw,
-    utilities::{AttributeControl, AttributePermissions, BleUuid},
+    utilities::{
+        AttributeControl, AttributePermissions, AuthorizationCallback, AuthorizationRequest,
+        BleUuid,
+    },
 };
 
 use esp_idf_sys::{
     esp_attr_control_t, esp_attr_value_t, esp_ble_gatts_add_char_descr,
     esp_ble_gatts_cb_param_t_gatts_read_evt_param, esp_ble_gatts_cb_param_t_gatts_write_evt_param,
-    esp_ble_gatts_set_attr_value, esp_nofail,
+    esp_ble_gatts_set_attr_value, esp_gatt_status_t, esp_gatt_status_t_ESP_GATT_INVALID_ATTR_LEN,
+    esp_nofail, ESP_GATT_RSP_BY_APP,
 };
 use log::{debug, info, warn};
 
@@ -23,6 +27,8 @@ pub struct Descriptor {
     pub(crate) control: AttributeControl,
     internal_control: esp_attr_control_t,
     pub(crate) write_callback: Option<fn(Vec<u8>, esp_ble_gatts_cb_param_t_gatts_write_evt_param)>,
+    pub(crate) authorization: Option<AuthorizationCallback>,
+    pub(crate) guarded: bool,
 }
 
 impl Descriptor {
@@ -38,6 +44,8 @@ impl Descriptor {
             control: AttributeControl::AutomaticResponse(vec![0]),
             internal_control: AttributeControl::AutomaticResponse(vec![0]).into(),
             write_callback: None,
+            authorization: None,
+            guarded: false,
         }
     }
 
@@ -62,7 +70,7 @@ impl Descriptor {
         &mut self,
         callback: C,
     ) -> &mut Self {
-        if !self.permissions.read_access {
+        if self.permissions.read_access.is_none() {
             warn!(
                 "Descriptor {} does not have read permissions. Ignoring read callback.",
                 self
@@ -82,7 +90,7 @@ impl Descriptor {
         &mut self,
         callback: fn(Vec<u8>, esp_ble_gatts_cb_param_t_gatts_write_evt_param),
     ) -> &mut Self {
-        if !self.permissions.write_access {
+        if self.permissions.write_access.is_none() {
             warn!(
                 "Descriptor {} does not have write permissions. Ignoring write callback.",
                 self
@@ -96,6 +104,21 @@ impl Descriptor {
         self
     }
 
+    /// Sets a callback that authorizes the access to this [`Descriptor`].
+    ///
+    /// The callback receives the context of the access, and returns whether it is allowed.
+    /// Denied accesses are answered with an "Insufficient Authorization" error.
+    /// See [`GattServer::on_authorize`] for the details.
+    ///
+    /// [`GattServer::on_authorize`]: crate::gatt_server::GattServer::on_authorize
+    pub fn on_authorize<C: Fn(&AuthorizationRequest) -> bool + Send + Sync + 'static>(
+        &mut self,
+        callback: C,
+    ) -> &mut Self {
+        self.authorization = Some(AuthorizationCallback::new(callback));
+        self
+    }
+
     /// Sets the value of the [`Descriptor`].
     pub fn set_value<T: Into<Vec<u8>>>(&mut self, value: T) -> &mut Self {
         self.value = value.into();
@@ -128,12 +151,59 @@ impl Descriptor {
     pub fn build(&self) -> Arc<RwLock<Self>> {
         Arc::new(RwLock::new(self.clone()))
     }
+
+    /// Returns whether the reads and writes of this [`Descriptor`] are answered by the application.
+    pub(crate) fn responds_by_app(&self) -> bool {
+        self.guarded || matches!(self.control, AttributeControl::ResponseByApp(_))
+    }
+
+    /// Returns the value to answer a read with.
+    pub(crate) fn read_value(
+        &self,
+        param: esp_ble_gatts_cb_param_t_gatts_read_evt_param,
+    ) -> Vec<u8> {
+        match &self.control {
+            AttributeControl::ResponseByApp(callback) => callback(param),
+            AttributeControl::AutomaticResponse(_) => self
+                .value
+                .get(usize::from(param.offset)..)
+                .unwrap_or_default()
+                .to_vec(),
+        }
+    }
+
+    /// Keeps a value written by a client, when the Bluetooth stack leaves the writes to the application.
+    ///
+    /// Returns the ATT error to answer with if the value is longer than the registered one.
+    pub(crate) fn store_written_value(&mut self, value: &[u8]) -> Result<(), esp_gatt_status_t> {
+        if !self.responds_by_app() {
+            return Ok(());
+        }
+
+        if let AttributeControl::AutomaticResponse(_) = self.control {
+            if value.len() > self.value.len() {
+                return Err(esp_gatt_status_t_ESP_GATT_INVALID_ATTR_LEN);
+            }
+
+            self.value = value.to_vec();
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn register_self(&mut self, service_handle: u16) {
         debug!(
             "Registering {} into service at handle 0x{:04x}.",
             self, service_handle
         );
 
+        // The value is kept by the application, and served from `value`.
+        if self.responds_by_app() {
+            self.internal_control = esp_attr_control_t {
+                auto_rsp: ESP_GATT_RSP_BY_APP as u8,
+            };
+        }
+
         #[allow(clippy::cast_possible_truncation)]
         unsafe {
             esp_nofail!(esp_ble_gatts_add_char_descr(
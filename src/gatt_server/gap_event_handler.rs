@@ -1,16 +1,23 @@
 use esp_idf_sys::{
-    esp_ble_gap_cb_param_t, esp_ble_gap_start_advertising, esp_bt_status_t_ESP_BT_STATUS_SUCCESS,
-    esp_gap_ble_cb_event_t, esp_gap_ble_cb_event_t_ESP_GAP_BLE_ADV_DATA_SET_COMPLETE_EVT,
+    esp_ble_gap_cb_param_t, esp_bt_status_t_ESP_BT_STATUS_SUCCESS, esp_gap_ble_cb_event_t,
+    esp_gap_ble_cb_event_t_ESP_GAP_BLE_ADV_DATA_SET_COMPLETE_EVT,
     esp_gap_ble_cb_event_t_ESP_GAP_BLE_ADV_START_COMPLETE_EVT,
     esp_gap_ble_cb_event_t_ESP_GAP_BLE_ADV_STOP_COMPLETE_EVT,
+    esp_gap_ble_cb_event_t_ESP_GAP_BLE_AUTH_CMPL_EVT, esp_gap_ble_cb_event_t_ESP_GAP_BLE_KEY_EVT,
+    esp_gap_ble_cb_event_t_ESP_GAP_BLE_NC_REQ_EVT, esp_gap_ble_cb_event_t_ESP_GAP_BLE_OOB_REQ_EVT,
+    esp_gap_ble_cb_event_t_ESP_GAP_BLE_PASSKEY_NOTIF_EVT,
+    esp_gap_ble_cb_event_t_ESP_GAP_BLE_PASSKEY_REQ_EVT,
     esp_gap_ble_cb_event_t_ESP_GAP_BLE_SCAN_RSP_DATA_SET_COMPLETE_EVT,
-    esp_gap_ble_cb_event_t_ESP_GAP_BLE_UPDATE_CONN_PARAMS_EVT, esp_nofail,
+    esp_gap_ble_cb_event_t_ESP_GAP_BLE_SEC_REQ_EVT,
+    esp_gap_ble_cb_event_t_ESP_GAP_BLE_SET_LOCAL_PRIVACY_COMPLETE_EVT,
+    esp_gap_ble_cb_event_t_ESP_GAP_BLE_SET_STATIC_RAND_ADDR_EVT,
+    esp_gap_ble_cb_event_t_ESP_GAP_BLE_UPDATE_CONN_PARAMS_EVT,
+    esp_gap_ble_cb_event_t_ESP_GAP_BLE_UPDATE_WHITELIST_COMPLETE_EVT,
 };
 
 use log::{debug, info, warn};
 
 use super::GattServer;
-use crate::leaky_box_raw;
 
 impl GattServer {
     pub(crate) extern "C" fn gap_event_handler(
@@ -24,26 +31,19 @@ impl GattServer {
                 debug!("BLE GAP advertisement data set complete.");
                 info!("Starting BLE GAP advertisement.");
 
-                unsafe {
-                    esp_nofail!(esp_ble_gap_start_advertising(leaky_box_raw!(
-                        self.advertisement_parameters
-                    )));
-                }
+                self.start_advertising();
             }
             esp_gap_ble_cb_event_t_ESP_GAP_BLE_SCAN_RSP_DATA_SET_COMPLETE_EVT => {
                 debug!("BLE GAP scan response data set complete.");
                 info!("Starting BLE GAP response advertisement.");
 
-                unsafe {
-                    esp_nofail!(esp_ble_gap_start_advertising(leaky_box_raw!(
-                        self.advertisement_parameters
-                    )));
-                }
+                self.start_advertising();
             }
             esp_gap_ble_cb_event_t_ESP_GAP_BLE_ADV_START_COMPLETE_EVT => {
                 let param = unsafe { (*param).adv_data_cmpl };
                 if param.status == esp_bt_status_t_ESP_BT_STATUS_SUCCESS {
                     debug!("BLE GAP advertisement started.");
+                    self.advertising = true;
                 } else {
                     warn!("BLE GAP advertisement start failed.");
                 }
@@ -52,6 +52,7 @@ impl GattServer {
                 let param = unsafe { (*param).adv_data_cmpl };
                 if param.status == esp_bt_status_t_ESP_BT_STATUS_SUCCESS {
                     debug!("BLE GAP advertisement stopped.");
+                    self.advertising = false;
                 } else {
                     warn!("BLE GAP advertisement stop failed.");
                 }
@@ -60,6 +61,56 @@ impl GattServer {
                 let param = unsafe { (*param).update_conn_params };
                 info!("Connection parameters updated: {:?}", param);
             }
+            esp_gap_ble_cb_event_t_ESP_GAP_BLE_SET_LOCAL_PRIVACY_COMPLETE_EVT => {
+                let param = unsafe { (*param).local_privacy_cmpl };
+                self.on_address_configured(param.status);
+            }
+            esp_gap_ble_cb_event_t_ESP_GAP_BLE_SET_STATIC_RAND_ADDR_EVT => {
+                let param = unsafe { (*param).set_rand_addr_cmpl };
+                self.on_address_configured(param.status);
+            }
+            esp_gap_ble_cb_event_t_ESP_GAP_BLE_UPDATE_WHITELIST_COMPLETE_EVT => {
+                let param = unsafe { (*param).update_whitelist_cmpl };
+                self.on_accept_list_updated(param);
+            }
+            esp_gap_ble_cb_event_t_ESP_GAP_BLE_SEC_REQ_EVT => {
+                let param = unsafe { (*param).ble_security.ble_req };
+                self.on_security_request(param);
+            }
+            esp_gap_ble_cb_event_t_ESP_GAP_BLE_AUTH_CMPL_EVT => {
+                let param = unsafe { (*param).ble_security.auth_cmpl };
+                self.on_authentication_complete(param);
+            }
+            esp_gap_ble_cb_event_t_ESP_GAP_BLE_KEY_EVT => {
+                let param = unsafe { (*param).ble_security.ble_key };
+                self.on_key(param);
+            }
+            esp_gap_ble_cb_event_t_ESP_GAP_BLE_PASSKEY_NOTIF_EVT => {
+                let param = unsafe { (*param).ble_security.key_notif };
+                self.on_passkey_notification(param);
+            }
+            esp_gap_ble_cb_event_t_ESP_GAP_BLE_PASSKEY_REQ_EVT => {
+                let param = unsafe { (*param).ble_security.ble_req };
+                self.on_passkey_request(param);
+            }
+            esp_gap_ble_cb_event_t_ESP_GAP_BLE_NC_REQ_EVT => {
+                let param = unsafe { (*param).ble_security.key_notif };
+                self.on_numeric_comparison(param);
+            }
+            esp_gap_ble_cb_event_t_ESP_GAP_BLE_OOB_REQ_EVT => {
+                let param = unsafe { (*param).ble_security.ble_req };
+                self.on_oob_request(param);
+            }
+            #[cfg(esp_idf_version_major = "5")]
+            esp_idf_sys::esp_gap_ble_cb_event_t_ESP_GAP_BLE_SC_OOB_REQ_EVT => {
+                let param = unsafe { (*param).ble_security.ble_req };
+                self.on_sc_oob_request(param);
+            }
+            #[cfg(esp_idf_version_major = "5")]
+            esp_idf_sys::esp_gap_ble_cb_event_t_ESP_GAP_BLE_SC_CR_LOC_OOB_EVT => {
+                let param = unsafe { (*param).ble_security.oob_data };
+                self.on_local_oob_data(param);
+            }
             _ => {
                 warn!("Unhandled GAP event: {:?}", event);
             }
@@ -0,0 +1,102 @@
+use esp_idf_sys::*;
+use log::info;
+
+use crate::{
+    gatt_server::{GattServer, GLOBAL_GATT_SERVER},
+    utilities::{FilterPolicy, GapMode, LIMITED_DISCOVERABLE_TIMEOUT},
+};
+
+impl GattServer {
+    /// Sets the GAP discoverability and connectability mode.
+    ///
+    /// The advertisement flags, type and filter policy are updated to match the mode.
+    /// This can be called while the server is running, for example to enter the
+    /// [`GapMode::LimitedDiscoverable`] mode when a pairing button is pressed.
+    ///
+    /// # Notes
+    ///
+    /// In [`GapMode::NonDiscoverable`] mode, the filter accept list is filled with the bonded peers.
+    pub fn gap_mode(&mut self, mode: GapMode) -> &mut Self {
+        // Any pending limited discoverable timeout is now stale.
+        self.gap_mode_generation = self.gap_mode_generation.wrapping_add(1);
+
+        if mode == GapMode::LimitedDiscoverable && self.gap_mode != GapMode::LimitedDiscoverable {
+            self.gap_mode_fallback = self.gap_mode;
+        }
+
+        self.gap_mode = mode;
+
+        let (flag, adv_type, filter_policy) = match mode {
+            GapMode::GeneralDiscoverable => (
+                ESP_BLE_ADV_FLAG_GEN_DISC | ESP_BLE_ADV_FLAG_BREDR_NOT_SPT,
+                esp_ble_adv_type_t_ADV_TYPE_IND,
+                FilterPolicy::AllowAll,
+            ),
+            GapMode::LimitedDiscoverable => (
+                ESP_BLE_ADV_FLAG_LIMIT_DISC | ESP_BLE_ADV_FLAG_BREDR_NOT_SPT,
+                esp_ble_adv_type_t_ADV_TYPE_IND,
+                FilterPolicy::AllowAll,
+            ),
+            GapMode::NonDiscoverable => (
+                ESP_BLE_ADV_FLAG_BREDR_NOT_SPT,
+                esp_ble_adv_type_t_ADV_TYPE_IND,
+                FilterPolicy::AcceptListOnly,
+            ),
+            GapMode::NonConnectable => (
+                ESP_BLE_ADV_FLAG_BREDR_NOT_SPT,
+                esp_ble_adv_type_t_ADV_TYPE_NONCONN_IND,
+                FilterPolicy::AllowAll,
+            ),
+        };
+
+        self.advertisement_data.flag = flag as u8;
+        self.scan_response_data.flag = flag as u8;
+        self.advertisement_parameters.adv_type = adv_type;
+        self.advertisement_parameters.adv_filter_policy = filter_policy.into();
+
+        if !self.started {
+            return self;
+        }
+
+        info!("Entering GAP mode {:?}.", mode);
+
+        if mode == GapMode::NonDiscoverable {
+            self.accept_list_from_bonds();
+        }
+
+        if mode == GapMode::LimitedDiscoverable {
+            self.schedule_limited_discoverable_timeout();
+        }
+
+        if self.advertisement_configured {
+            // The new flags and parameters are applied when the advertisement data is set again.
+            unsafe {
+                esp_nofail!(esp_ble_gap_stop_advertising());
+                esp_nofail!(esp_ble_gap_config_adv_data(&mut self.advertisement_data));
+            }
+        }
+
+        self
+    }
+
+    /// Applies the configured GAP mode when the server starts.
+    pub(crate) fn configure_gap_mode(&mut self) {
+        self.gap_mode(self.gap_mode);
+    }
+
+    /// Leaves the limited discoverable mode after `TGAP(lim_adv_timeout)`, unless the mode changes in the meantime.
+    fn schedule_limited_discoverable_timeout(&self) {
+        let generation = self.gap_mode_generation;
+
+        std::thread::spawn(move || {
+            std::thread::sleep(LIMITED_DISCOVERABLE_TIMEOUT);
+
+            let mut server = GLOBAL_GATT_SERVER.lock().unwrap();
+            if server.gap_mode_generation == generation {
+                info!("Limited discoverable mode timed out.");
+                let fallback = server.gap_mode_fallback;
+                server.gap_mode(fallback);
+            }
+        });
+    }
+}
@@ -33,6 +33,13 @@ impl GattServer {
                 // Do not pass this event to the profile handlers.
                 return;
             }
+            esp_gatts_cb_event_t_ESP_GATTS_CONGEST_EVT => {
+                let param = unsafe { (*param).congest };
+                self.on_congest(param);
+
+                // Do not pass this event to the profile handlers.
+                return;
+            }
             esp_gatts_cb_event_t_ESP_GATTS_MTU_EVT => {
                 let param = unsafe { (*param).mtu };
                 self.on_mtu_change(param);
@@ -73,7 +80,7 @@ impl GattServer {
                 profile
                     .write()
                     .unwrap()
-                    .gatts_event_handler(event, gatts_if, param);
+                    .gatts_event_handler(self, event, gatts_if, param);
             }
         });
     }
@@ -83,6 +90,7 @@ impl Profile {
     /// Profile-specific GATT server event loop.
     fn gatts_event_handler(
         &mut self,
+        server: &GattServer,
         event: esp_gatts_cb_event_t,
         gatts_if: esp_gatt_if_t,
         param: *mut esp_ble_gatts_cb_param_t,
@@ -97,13 +105,18 @@ impl Profile {
             esp_gatts_cb_event_t_ESP_GATTS_CREATE_EVT => {
                 let param = unsafe { (*param).create };
 
-                self.on_create(param);
+                self.on_create(server, param);
             }
             esp_gatts_cb_event_t_ESP_GATTS_START_EVT => {
                 let param = unsafe { (*param).start };
 
                 self.on_start(param);
             }
+            esp_gatts_cb_event_t_ESP_GATTS_ADD_INCL_SRVC_EVT => {
+                let param = unsafe { (*param).add_incl_srvc };
+
+                self.on_incl_srvc_add(param);
+            }
             esp_gatts_cb_event_t_ESP_GATTS_ADD_CHAR_EVT => {
                 let param = unsafe { (*param).add_char };
 
@@ -117,18 +130,22 @@ impl Profile {
             esp_gatts_cb_event_t_ESP_GATTS_WRITE_EVT => {
                 let param = unsafe { (*param).write };
 
-                self.on_write(gatts_if, param);
+                self.on_write(server, gatts_if, param);
+            }
+            esp_gatts_cb_event_t_ESP_GATTS_EXEC_WRITE_EVT => {
+                let param = unsafe { (*param).exec_write };
+
+                self.on_exec_write(gatts_if, param);
             }
             esp_gatts_cb_event_t_ESP_GATTS_READ_EVT => {
                 let param = unsafe { (*param).read };
 
-                self.on_read(gatts_if, param);
+                self.on_read(server, gatts_if, param);
             }
             esp_gatts_cb_event_t_ESP_GATTS_CONF_EVT => {
-                let _param = unsafe { (*param).conf };
+                let param = unsafe { (*param).conf };
 
-                // TODO: on_conf.
-                debug!("Received confirmation event.");
+                self.on_conf(param);
             }
             _ => {
                 warn!("Unhandled GATT server event: {:?}", event);
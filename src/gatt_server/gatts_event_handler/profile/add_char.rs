@@ -22,6 +22,7 @@ impl Profile {
                 param.attr_handle
             );
             characteristic.write().unwrap().attribute_handle = Some(param.attr_handle);
+            characteristic.write().unwrap().gatts_if = self.interface;
             characteristic.write().unwrap().register_descriptors();
         } else {
             warn!("GATT characteristic registration failed.");
@@ -33,6 +33,10 @@ impl Profile {
                 param.attr_handle
             );
             descriptor.write().unwrap().attribute_handle = Some(param.attr_handle);
+            service
+                .read()
+                .unwrap()
+                .register_cccd(descriptor, param.attr_handle);
         } else {
             warn!("GATT descriptor registration failed.");
         }
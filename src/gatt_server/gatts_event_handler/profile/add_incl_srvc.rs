@@ -0,0 +1,34 @@
+use crate::gatt_server::Profile;
+use esp_idf_sys::*;
+use log::{info, warn};
+
+impl Profile {
+    pub(crate) fn on_incl_srvc_add(
+        &mut self,
+        param: esp_ble_gatts_cb_param_t_gatts_add_incl_srvc_evt_param,
+    ) {
+        let Some(service) = self.get_service(param.service_handle) else {
+            warn!(
+                "Cannot find service described by handle 0x{:04x} received in include creation event.",
+                param.service_handle
+            );
+            return;
+        };
+
+        if param.status == esp_gatt_status_t_ESP_GATT_OK {
+            info!(
+                "GATT include declaration of {} registered at attribute handle 0x{:04x}.",
+                service.read().unwrap(),
+                param.attr_handle
+            );
+            service
+                .write()
+                .unwrap()
+                .include_handles
+                .push(Some(param.attr_handle));
+        } else {
+            warn!("GATT include declaration registration failed.");
+            service.write().unwrap().include_handles.push(None);
+        }
+    }
+}
@@ -1 +1,19 @@
+use crate::gatt_server::{
+    indications::on_indication_confirmed, notifications::on_notification_sent, Profile,
+};
+use esp_idf_sys::*;
+use log::debug;
 
+impl Profile {
+    pub(crate) fn on_conf(&self, param: esp_ble_gatts_cb_param_t_gatts_conf_evt_param) {
+        debug!(
+            "Received confirmation for handle 0x{:04x} on connection {}.",
+            param.handle, param.conn_id
+        );
+
+        // Notifications are reported as soon as they are sent, indications once the client confirms them.
+        if !on_notification_sent(param.conn_id, param.handle, param.status) {
+            on_indication_confirmed(param.conn_id, param.handle, param.status);
+        }
+    }
+}
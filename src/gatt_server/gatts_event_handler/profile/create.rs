@@ -1,10 +1,14 @@
-use crate::gatt_server::Profile;
+use crate::gatt_server::{GattServer, Profile, Service};
 use crate::utilities::BleUuid;
 use esp_idf_sys::*;
 use log::{info, warn};
 
 impl Profile {
-    pub(crate) fn on_create(&mut self, param: esp_ble_gatts_cb_param_t_gatts_create_evt_param) {
+    pub(crate) fn on_create(
+        &mut self,
+        server: &GattServer,
+        param: esp_ble_gatts_cb_param_t_gatts_create_evt_param,
+    ) {
         let Some(service) = self.get_service_by_id(param.service_id.id) else {
             warn!("Cannot find service with service identifier {} received in service creation event.", BleUuid::from(param.service_id.id));
             return;
@@ -25,7 +29,7 @@ impl Profile {
                 ));
             }
 
-            service.write().unwrap().register_characteristics();
+            Service::register_characteristics(&service, server.authorization.is_some());
         } else {
             warn!("GATT service registration failed.");
         }
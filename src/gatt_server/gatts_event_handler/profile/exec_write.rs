@@ -0,0 +1,58 @@
+use crate::gatt_server::Profile;
+use crate::gatt_server::{attribute::send_response, prepared_writes::take_prepared_writes};
+use esp_idf_sys::*;
+use log::{debug, warn};
+
+impl Profile {
+    pub(crate) fn on_exec_write(
+        &mut self,
+        gatts_if: esp_gatt_if_t,
+        param: esp_ble_gatts_cb_param_t_gatts_exec_write_evt_param,
+    ) {
+        // The Bluetooth stack executes the long writes of the attributes it answers itself.
+        let Some(writes) = take_prepared_writes(gatts_if, param.conn_id) else {
+            return;
+        };
+
+        let mut status = esp_gatt_status_t_ESP_GATT_OK;
+
+        if u32::from(param.exec_write_flag) == ESP_GATT_PREP_WRITE_EXEC {
+            for (handle, value) in writes {
+                let Some(attribute) = self.find_attribute(handle) else {
+                    continue;
+                };
+
+                debug!("Executing long write of {}.", attribute);
+
+                let result = value.and_then(|mut value| {
+                    // The callbacks receive the whole value as a single write.
+                    let write_param = esp_ble_gatts_cb_param_t_gatts_write_evt_param {
+                        conn_id: param.conn_id,
+                        trans_id: param.trans_id,
+                        bda: param.bda,
+                        handle,
+                        offset: 0,
+                        need_rsp: false,
+                        is_prep: false,
+                        len: value.len() as u16,
+                        value: value.as_mut_ptr(),
+                    };
+
+                    attribute.write(value.clone(), write_param)
+                });
+
+                if let Err(error) = result {
+                    warn!(
+                        "Long write of handle 0x{:04x} was rejected ({:#04x}).",
+                        handle, error
+                    );
+                    status = error;
+                }
+            }
+        } else {
+            debug!("Long write cancelled by the client.");
+        }
+
+        send_response(gatts_if, param.conn_id, param.trans_id, status, 0, 0, &[]);
+    }
+}
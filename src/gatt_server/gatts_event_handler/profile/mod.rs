@@ -1,7 +1,9 @@
 mod add_char;
 mod add_char_descr;
+mod add_incl_srvc;
 mod conf;
 mod create;
+mod exec_write;
 mod read;
 mod reg;
 mod start;
@@ -1,161 +1,105 @@
-use crate::gatt_server::Profile;
-use crate::utilities::AttributeControl;
+use crate::gatt_server::{
+    attribute::send_response,
+    authorization::send_error_response,
+    prepared_writes::{deny_prepared_write, prepare_write},
+    GattServer, Profile,
+};
+use crate::utilities::AttributeOperation;
 use esp_idf_sys::*;
-use log::debug;
+use log::{debug, warn};
 
 impl Profile {
-    #[allow(clippy::too_many_lines)]
     pub(crate) fn on_write(
         &mut self,
+        server: &GattServer,
         gatts_if: esp_gatt_if_t,
         param: esp_ble_gatts_cb_param_t_gatts_write_evt_param,
     ) {
-        for service in &self.services {
-            service
-                .read()
-                .unwrap()
-                .characteristics
-                .iter()
-                .for_each(|characteristic| {
-                    if characteristic.read().unwrap().attribute_handle == Some(param.handle) {
-                        debug!(
-                            "Received write event for characteristic {}.",
-                            characteristic.read().unwrap()
-                        );
+        let Some(attribute) = self.find_attribute(param.handle) else {
+            return;
+        };
 
-                        // If the characteristic has a write handler, call it.
-                        if let Some(write_callback) = &characteristic.read().unwrap().write_callback
-                        {
-                            let value = unsafe {
-                                std::slice::from_raw_parts(param.value, param.len as usize)
-                            }
-                            .to_vec();
+        debug!("Received write event for {}.", attribute);
 
-                            write_callback(value, param);
+        let by_app = attribute.responds_by_app();
 
-                            // Send response if needed.
-                            if param.need_rsp {
-                                if let AttributeControl::ResponseByApp(read_callback) =
-                                    &characteristic.read().unwrap().control
-                                {
-                                    // Simulate a read operation.
-                                    let param_as_read_operation =
-                                        esp_ble_gatts_cb_param_t_gatts_read_evt_param {
-                                            bda: param.bda,
-                                            conn_id: param.conn_id,
-                                            handle: param.handle,
-                                            need_rsp: param.need_rsp,
-                                            offset: param.offset,
-                                            trans_id: param.trans_id,
-                                            ..Default::default()
-                                        };
+        if let Err(status) = attribute.check_access(
+            server,
+            param.conn_id,
+            param.handle,
+            AttributeOperation::Write,
+        ) {
+            if by_app && param.is_prep {
+                deny_prepared_write(gatts_if, param.conn_id);
+            }
 
-                                    // Get value.
-                                    let value = read_callback(param_as_read_operation);
-
-                                    // Extend the response to the maximum length.
-                                    let mut response = [0u8; 600];
-                                    response[..value.len()].copy_from_slice(&value);
-
-                                    let mut esp_rsp = esp_gatt_rsp_t {
-                                        attr_value: esp_gatt_value_t {
-                                            auth_req: 0,
-                                            handle: param.handle,
-                                            len: value.len() as u16,
-                                            offset: 0,
-                                            value: response,
-                                        },
-                                    };
-
-                                    unsafe {
-                                        esp_nofail!(esp_ble_gatts_send_response(
-                                            gatts_if,
-                                            param.conn_id,
-                                            param.trans_id,
-                                            esp_gatt_status_t_ESP_GATT_OK,
-                                            &mut esp_rsp
-                                        ));
-                                    }
-                                }
-                            }
-                        }
-                    } else {
-                        characteristic
-                            .read()
-                            .unwrap()
-                            .descriptors
-                            .iter()
-                            .for_each(|descriptor| {
-                                if descriptor.read().unwrap().attribute_handle == Some(param.handle)
-                                {
-                                    debug!(
-                                        "Received write event for descriptor {}.",
-                                        descriptor.read().unwrap()
-                                    );
+            deny_write(gatts_if, param, by_app, status);
+            return;
+        }
 
-                                    if let Some(write_callback) =
-                                        descriptor.read().unwrap().write_callback
-                                    {
-                                        let value = unsafe {
-                                            std::slice::from_raw_parts(
-                                                param.value,
-                                                param.len as usize,
-                                            )
-                                        }
-                                        .to_vec();
+        let value = unsafe { std::slice::from_raw_parts(param.value, param.len as usize) }.to_vec();
 
-                                        write_callback(value, param);
+        // Long writes are applied once the client executes them.
+        if by_app && param.is_prep {
+            prepare_write(gatts_if, param.conn_id, param.handle, param.offset, &value);
 
-                                        // Send response if needed.
-                                        if param.need_rsp {
-                                            if let AttributeControl::ResponseByApp(read_callback) =
-                                                &descriptor.read().unwrap().control
-                                            {
-                                                // Simulate a read operation.
-                                                let param_as_read_operation =
-                                                    esp_ble_gatts_cb_param_t_gatts_read_evt_param {
-                                                        bda: param.bda,
-                                                        conn_id: param.conn_id,
-                                                        handle: param.handle,
-                                                        need_rsp: param.need_rsp,
-                                                        offset: param.offset,
-                                                        trans_id: param.trans_id,
-                                                        ..Default::default()
-                                                    };
+            // The prepared part is echoed back, so that the client can check it.
+            if param.need_rsp {
+                send_response(
+                    gatts_if,
+                    param.conn_id,
+                    param.trans_id,
+                    esp_gatt_status_t_ESP_GATT_OK,
+                    param.handle,
+                    param.offset,
+                    &value,
+                );
+            }
+            return;
+        }
 
-                                                // Get value.
-                                                let value = read_callback(param_as_read_operation);
+        if let Err(status) = attribute.write(value, param) {
+            deny_write(gatts_if, param, by_app, status);
+            return;
+        }
 
-                                                // Extend the response to the maximum length.
-                                                let mut response = [0u8; 600];
-                                                response[..value.len()].copy_from_slice(&value);
+        if by_app && param.need_rsp {
+            send_response(
+                gatts_if,
+                param.conn_id,
+                param.trans_id,
+                esp_gatt_status_t_ESP_GATT_OK,
+                param.handle,
+                0,
+                &[],
+            );
+        }
+    }
+}
 
-                                                let mut esp_rsp = esp_gatt_rsp_t {
-                                                    attr_value: esp_gatt_value_t {
-                                                        auth_req: 0,
-                                                        handle: param.handle,
-                                                        len: value.len() as u16,
-                                                        offset: 0,
-                                                        value: response,
-                                                    },
-                                                };
+/// Answers a denied write, if the application is in charge of the response.
+fn deny_write(
+    gatts_if: esp_gatt_if_t,
+    param: esp_ble_gatts_cb_param_t_gatts_write_evt_param,
+    by_app: bool,
+    status: esp_gatt_status_t,
+) {
+    if !param.need_rsp {
+        return;
+    }
 
-                                                unsafe {
-                                                    esp_nofail!(esp_ble_gatts_send_response(
-                                                        gatts_if,
-                                                        param.conn_id,
-                                                        param.trans_id,
-                                                        esp_gatt_status_t_ESP_GATT_OK,
-                                                        &mut esp_rsp
-                                                    ));
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            });
-                    }
-                });
-        }
+    if by_app {
+        send_error_response(
+            gatts_if,
+            param.conn_id,
+            param.trans_id,
+            param.handle,
+            status,
+        );
+    } else {
+        warn!(
+            "Write to handle 0x{:04x} was denied, but the Bluetooth stack already accepted it.",
+            param.handle
+        );
     }
 }
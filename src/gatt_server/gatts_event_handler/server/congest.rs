@@ -0,0 +1,17 @@
+use crate::gatt_server::{notifications::set_congested, GattServer};
+use log::debug;
+
+impl GattServer {
+    #[allow(clippy::unused_self)]
+    pub(crate) fn on_congest(
+        &self,
+        param: esp_idf_sys::esp_ble_gatts_cb_param_t_gatts_congest_evt_param,
+    ) {
+        debug!(
+            "Connection {} congested: {}.",
+            param.conn_id, param.congested
+        );
+
+        set_congested(param.conn_id, param.congested);
+    }
+}
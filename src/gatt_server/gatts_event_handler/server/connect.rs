@@ -1,4 +1,4 @@
-use crate::gatt_server::GattServer;
+use crate::gatt_server::{notifications::open_notification_queue, GattServer};
 use crate::utilities::Connection;
 use log::info;
 
@@ -7,7 +7,35 @@ impl GattServer {
         &mut self,
         param: esp_idf_sys::esp_ble_gatts_cb_param_t_gatts_connect_evt_param,
     ) {
-        info!("GATT client {} connected.", Connection::from(param));
-        self.active_connections.insert(param.into());
+        let connection = Connection::from(param);
+        info!("GATT client {} connected.", connection);
+        connection.insert();
+        open_notification_queue(connection.id);
+
+        // Cancel any pending advertisement restart.
+        self.advertising_generation = self.advertising_generation.wrapping_add(1);
+
+        // The controller stops advertising when a connection is established.
+        // Keep advertising for other clients, until the connection limit is reached.
+        self.advertising = false;
+        self.start_advertising();
+
+        // A bonded client keeps the subscriptions stored with its bond.
+        self.report_subscriptions(connection);
+
+        self.request_security(&connection);
+    }
+
+    /// Reports the subscriptions of a client stored in the CCCDs, once it is identified.
+    pub(crate) fn report_subscriptions(&self, connection: Connection) {
+        for profile in &self.profiles {
+            for service in &profile.read().unwrap().services {
+                for characteristic in &service.read().unwrap().characteristics {
+                    let characteristic = characteristic.read().unwrap();
+                    let current = characteristic.subscription_mode(&connection);
+                    characteristic.report_subscription(connection, current);
+                }
+            }
+        }
     }
 }
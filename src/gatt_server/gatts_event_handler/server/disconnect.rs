@@ -1,4 +1,8 @@
-use crate::gatt_server::GattServer;
+use crate::gatt_server::{
+    custom_attributes::forget_cccd_values, indications::drop_indications,
+    notifications::close_notification_queue, prepared_writes::drop_prepared_writes, GattServer,
+};
+use crate::utilities::{Connection, DisconnectReason};
 use log::info;
 
 impl GattServer {
@@ -6,15 +10,40 @@ impl GattServer {
         &mut self,
         param: esp_idf_sys::esp_ble_gatts_cb_param_t_gatts_disconnect_evt_param,
     ) {
+        let reason = DisconnectReason::from(param.reason);
+
         info!(
-            "GATT client {:02X?} disconnected.",
-            param.remote_bda.to_vec()
+            "GATT client {:02X?} disconnected ({:?}).",
+            param.remote_bda.to_vec(),
+            reason
         );
 
-        self.active_connections.remove(&param.into());
+        self.key_sizes.remove(&param.remote_bda);
+        let connection =
+            Connection::remove(param.conn_id).unwrap_or_else(|| Connection::from(param));
+        drop_indications(param.conn_id);
+        close_notification_queue(param.conn_id);
+        drop_prepared_writes(param.conn_id);
+
+        self.report_unsubscriptions(connection);
+        forget_cccd_values(param.remote_bda);
+
+        // The values written by the client are not left waiting for their debounce delay.
+        Self::flush_persistent_values();
+        self.apply_reconnection_policy(connection, reason);
+    }
 
-        unsafe {
-            esp_idf_sys::esp_ble_gap_start_advertising(&mut self.advertisement_parameters);
+    /// Reports the subscriptions of a disconnected client as ended.
+    fn report_unsubscriptions(&self, connection: Connection) {
+        for profile in &self.profiles {
+            for service in &profile.read().unwrap().services {
+                for characteristic in &service.read().unwrap().characteristics {
+                    characteristic
+                        .read()
+                        .unwrap()
+                        .report_subscription(connection, None);
+                }
+            }
         }
     }
 }
@@ -1,3 +1,4 @@
+mod congest;
 mod connect;
 mod disconnect;
 mod mtu;
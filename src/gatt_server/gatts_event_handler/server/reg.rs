@@ -20,21 +20,7 @@ impl GattServer {
 
             profile.write().unwrap().interface = Some(gatts_if);
 
-            if !self.advertisement_configured {
-                unsafe {
-                    esp_nofail!(esp_ble_gap_set_device_name(
-                        self.device_name.as_ptr().cast::<i8>()
-                    ));
-
-                    self.advertisement_configured = true;
-
-                    // Advertisement data.
-                    esp_nofail!(esp_ble_gap_config_adv_data(&mut self.advertisement_data));
-
-                    // Scan response data.
-                    esp_nofail!(esp_ble_gap_config_adv_data(&mut self.scan_response_data));
-                }
-            }
+            self.configure_advertisement();
         }
     }
 }
@@ -1,5 +1,10 @@
-use crate::gatt_server::GattServer;
-use crate::utilities::BleUuid;
+use crate::gatt_server::{
+    characteristic::ValueChange,
+    indications::{enqueue_indication, PendingIndication},
+    notifications::{send_notification, PendingNotification},
+    GattServer,
+};
+use crate::utilities::{Connection, DeliveryMode, IndicationListener};
 use esp_idf_sys::*;
 use log::{debug, warn};
 
@@ -37,78 +42,79 @@ impl GattServer {
             characteristic.read().unwrap()
         );
 
-        for connection in self.active_connections.clone() {
-            // Get the current status of the CCCD via a fake read operation.
-            let simulated_read_param = esp_ble_gatts_cb_param_t_gatts_read_evt_param {
-                bda: connection.remote_bda,
-                conn_id: connection.id,
-                handle: characteristic
-                    .read()
-                    .unwrap()
-                    .descriptors
-                    .iter()
-                    .find(|desc| desc.read().unwrap().uuid == BleUuid::Uuid16(0x2902))
-                    .unwrap()
-                    .read()
-                    .unwrap()
-                    .attribute_handle
-                    .unwrap(),
-                ..Default::default()
-            };
+        // The outcomes of the indications are reported to the receipt of this value change, if any.
+        let change = characteristic.write().unwrap().pending_changes.pop_front();
+        let (receipt, preferred) = match change {
+            Some(ValueChange::Broadcast { receipt, preferred }) => (receipt, preferred),
+            Some(ValueChange::Silent) => {
+                debug!("The clients were already notified.");
+                return;
+            }
+            None => (None, characteristic.read().unwrap().preferred_delivery),
+        };
+        let listener = IndicationListener {
+            callback: characteristic.read().unwrap().indication_callback.clone(),
+            receipt,
+        };
 
-            let status = characteristic
-                .read()
-                .unwrap()
-                .get_cccd_status(simulated_read_param);
+        for connection in Connection::connected() {
+            let status = characteristic.read().unwrap().cccd_status(&connection);
 
-            // Check that the status is not None, otherwise bail.
-            let Some((notification, indication)) = status else { return; };
+            // Check that the status is not None, otherwise skip this client.
+            let Some((notification, indication)) = status else {
+                continue;
+            };
             let properties = characteristic.read().unwrap().properties;
 
-            let mut internal_value = characteristic.write().unwrap().internal_value.clone();
+            // Each client gets the mode it enabled in its CCCD, or the preferred one if it enabled both.
+            let mode = match (
+                properties.notify && notification,
+                properties.indicate && indication,
+            ) {
+                (true, true) => preferred,
+                (true, false) => DeliveryMode::Notification,
+                (false, true) => DeliveryMode::Indication,
+                (false, false) => continue,
+            };
+
+            let internal_value = characteristic.write().unwrap().internal_value.clone();
 
-            if properties.indicate && indication {
+            if mode == DeliveryMode::Indication {
                 debug!(
-                    "Indicating {} value change to {:02X?}.",
+                    "Indicating {} value change to {}.",
                     characteristic.read().unwrap(),
-                    connection.id
+                    connection
                 );
-                let result = unsafe {
-                    esp!(esp_ble_gatts_send_indicate(
-                        gatts_if,
-                        connection.id,
-                        param.attr_handle,
-                        internal_value.len() as u16,
-                        internal_value.as_mut_slice().as_mut_ptr(),
-                        true
-                    ))
-                };
-
-                if result.is_err() {
-                    warn!(
-                        "Failed to indicate value change: {}.",
-                        result.err().unwrap()
-                    );
-                }
-            } else if properties.notify && notification {
+
+                // Only one indication can be outstanding per connection, so it is queued.
+                enqueue_indication(PendingIndication {
+                    connection,
+                    gatts_if,
+                    handle: param.attr_handle,
+                    value: internal_value,
+                    listener: listener.clone(),
+                });
+            } else {
                 debug!(
                     "Notifying {} value change to {}.",
                     characteristic.read().unwrap(),
                     connection
                 );
-                let result = unsafe {
-                    esp!(esp_ble_gatts_send_indicate(
+
+                // Notifications wait in a queue while the link is congested.
+                // This runs in the Bluetooth stack's context, so it must not block on a full queue.
+                let result = send_notification(
+                    PendingNotification {
+                        conn_id: connection.id,
                         gatts_if,
-                        connection.id,
-                        param.attr_handle,
-                        internal_value.len() as u16,
-                        internal_value.as_mut_slice().as_mut_ptr(),
-                        false
-                    ))
-                };
-
-                if result.is_err() {
-                    warn!("Failed to notify value change: {}.", result.err().unwrap());
+                        handle: param.attr_handle,
+                        value: internal_value,
+                    },
+                    false,
+                );
+
+                if let Err(error) = result {
+                    warn!("Failed to notify value change: {}.", error);
                 }
             }
         }
@@ -0,0 +1,226 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Condvar, Mutex},
+    time::Instant,
+};
+
+use esp_idf_sys::*;
+use lazy_static::lazy_static;
+use log::warn;
+
+use crate::utilities::{Connection, IndicationListener, IndicationOutcome, INDICATION_TIMEOUT};
+
+lazy_static! {
+    /// The indication queue of every connection, by connection identifier.
+    ///
+    /// Only one indication can be outstanding on a connection, so indications wait in a queue
+    /// until the previous one is confirmed. The queues are kept apart from the server,
+    /// so that the confirmation timeouts do not need its lock.
+    static ref INDICATION_QUEUES: Mutex<HashMap<u16, IndicationQueue>> = Mutex::new(HashMap::new());
+
+    /// Wakes up the timeout workers when an outstanding indication changes.
+    static ref INDICATION_CHANGED: Condvar = Condvar::new();
+}
+
+/// An indication waiting to be sent or confirmed.
+#[derive(Debug)]
+pub(crate) struct PendingIndication {
+    pub(crate) connection: Connection,
+    pub(crate) gatts_if: esp_gatt_if_t,
+    pub(crate) handle: u16,
+    pub(crate) value: Vec<u8>,
+    pub(crate) listener: IndicationListener,
+}
+
+#[derive(Debug, Default)]
+struct IndicationQueue {
+    in_flight: Option<PendingIndication>,
+    /// When the outstanding indication times out.
+    deadline: Option<Instant>,
+    /// Whether a worker watches the timeouts of the connection.
+    watched: bool,
+    /// Whether an indication timed out. No more indications can be sent on the connection then.
+    timed_out: bool,
+    queue: VecDeque<PendingIndication>,
+}
+
+/// Queues an indication, and sends it right away if no other indication is outstanding on its connection.
+///
+/// After a timeout, the indication is reported as timed out instead.
+pub(crate) fn enqueue_indication(indication: PendingIndication) {
+    let conn_id = indication.connection.id;
+
+    let rejected = {
+        let mut queues = INDICATION_QUEUES.lock().unwrap();
+        let queue = queues.entry(conn_id).or_default();
+
+        if queue.timed_out {
+            Some(indication)
+        } else {
+            queue.queue.push_back(indication);
+            None
+        }
+    };
+
+    match rejected {
+        Some(indication) => indication
+            .listener
+            .report(indication.connection, IndicationOutcome::TimedOut),
+        None => send_next_indication(conn_id),
+    }
+}
+
+/// Completes the outstanding indication of a connection, and sends the next one.
+pub(crate) fn on_indication_confirmed(conn_id: u16, handle: u16, status: esp_gatt_status_t) {
+    let indication = INDICATION_QUEUES
+        .lock()
+        .unwrap()
+        .get_mut(&conn_id)
+        .and_then(|queue| {
+            if queue.in_flight.as_ref()?.handle == handle {
+                queue.deadline = None;
+                queue.in_flight.take()
+            } else {
+                None
+            }
+        });
+
+    let Some(indication) = indication else {
+        warn!(
+            "Unexpected confirmation for handle 0x{:04x} on connection {}.",
+            handle, conn_id
+        );
+        return;
+    };
+
+    let outcome = if status == esp_gatt_status_t_ESP_GATT_OK {
+        IndicationOutcome::Confirmed
+    } else {
+        warn!("Indication failed, error code: {:04x}.", status);
+        IndicationOutcome::Failed
+    };
+
+    indication.listener.report(indication.connection, outcome);
+    send_next_indication(conn_id);
+}
+
+/// Drops the indications of a disconnected client.
+pub(crate) fn drop_indications(conn_id: u16) {
+    let Some(queue) = INDICATION_QUEUES.lock().unwrap().remove(&conn_id) else {
+        return;
+    };
+    INDICATION_CHANGED.notify_all();
+
+    for indication in queue.in_flight.into_iter().chain(queue.queue) {
+        indication
+            .listener
+            .report(indication.connection, IndicationOutcome::Disconnected);
+    }
+}
+
+/// Sends the next queued indication of a connection, unless one is outstanding.
+///
+/// The outcomes are reported after releasing the queues, so that the callbacks can queue new indications.
+fn send_next_indication(conn_id: u16) {
+    let mut failed = Vec::new();
+
+    {
+        let mut queues = INDICATION_QUEUES.lock().unwrap();
+        let Some(queue) = queues.get_mut(&conn_id) else {
+            return;
+        };
+
+        while queue.in_flight.is_none() {
+            let Some(mut indication) = queue.queue.pop_front() else {
+                break;
+            };
+
+            let result = unsafe {
+                esp!(esp_ble_gatts_send_indicate(
+                    indication.gatts_if,
+                    conn_id,
+                    indication.handle,
+                    indication.value.len() as u16,
+                    indication.value.as_mut_ptr(),
+                    true
+                ))
+            };
+
+            if let Err(error) = result {
+                warn!("Failed to indicate value change: {}.", error);
+                failed.push(indication);
+                continue;
+            }
+
+            queue.in_flight = Some(indication);
+            queue.deadline = Some(Instant::now() + INDICATION_TIMEOUT);
+
+            if !queue.watched {
+                queue.watched = true;
+                std::thread::spawn(move || watch_timeouts(conn_id));
+            }
+        }
+    }
+
+    INDICATION_CHANGED.notify_all();
+
+    for indication in failed {
+        indication
+            .listener
+            .report(indication.connection, IndicationOutcome::Failed);
+    }
+}
+
+/// Gives up on the indications of a connection once the outstanding one is not confirmed in time.
+///
+/// A single worker watches a connection, as long as it has an outstanding indication.
+/// The ATT protocol forbids sending anything more on the connection after a timeout,
+/// so the queued indications are reported as timed out as well.
+fn watch_timeouts(conn_id: u16) {
+    let mut queues = INDICATION_QUEUES.lock().unwrap();
+
+    loop {
+        let Some(queue) = queues.get_mut(&conn_id) else {
+            return;
+        };
+
+        let Some(deadline) = queue.deadline else {
+            queue.watched = false;
+            return;
+        };
+
+        let now = Instant::now();
+        if now < deadline {
+            queues = INDICATION_CHANGED
+                .wait_timeout(queues, deadline - now)
+                .unwrap()
+                .0;
+            continue;
+        }
+
+        queue.deadline = None;
+        queue.watched = false;
+        queue.timed_out = true;
+        let indications: Vec<PendingIndication> = queue
+            .in_flight
+            .take()
+            .into_iter()
+            .chain(queue.queue.drain(..))
+            .collect();
+        drop(queues);
+
+        warn!(
+            "Indication on connection {} was not confirmed in time. Dropping {} indications.",
+            conn_id,
+            indications.len()
+        );
+
+        for indication in indications {
+            indication
+                .listener
+                .report(indication.connection, IndicationOutcome::TimedOut);
+        }
+
+        return;
+    }
+}
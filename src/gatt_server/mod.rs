@@ -3,8 +3,9 @@
 #![allow(clippy::cast_possible_truncation)]
 
 use std::{
-    collections::HashSet,
+    collections::HashMap,
     sync::{Arc, Mutex, RwLock},
+    time::Duration,
 };
 
 use esp_idf_sys::*;
@@ -12,8 +13,12 @@ use lazy_static::lazy_static;
 use log::{info, warn};
 
 use crate::{
+    gatt_server::custom_attributes::remove_orphaned_cccd_values,
     leaky_box_raw,
-    utilities::{Appearance, Connection},
+    utilities::{
+        AddressMode, Appearance, AuthorizationCallback, BleAddress, GapMode, ReconnectionPolicy,
+        SecurityConfig, DEFAULT_PAIRING_TIMEOUT, DEFAULT_RPA_TIMEOUT,
+    },
 };
 
 pub use characteristic::Characteristic;
@@ -21,6 +26,8 @@ pub use descriptor::Descriptor;
 pub use profile::Profile;
 pub use service::Service;
 
+pub(crate) use notifications::{notification_stats, wait_for_room};
+
 // Structs.
 mod characteristic;
 mod descriptor;
@@ -30,20 +37,54 @@ mod service;
 // Custom stuff.
 mod custom_attributes;
 
+// Attribute access.
+mod attribute;
+mod prepared_writes;
+
+// Persistence.
+mod persistence;
+mod service_changed;
+mod storage;
+
 // Event handler.
 mod gap_event_handler;
 mod gatts_event_handler;
 
+// Privacy.
+mod privacy;
+
+// Discoverability and connectability.
+mod accept_list;
+mod gap_mode;
+mod reconnection;
+
+// Connection management.
+mod connections;
+
+// Notifications and indications.
+mod indications;
+mod notifications;
+
+// Security and pairing.
+mod authorization;
+mod bond_backup;
+mod bonding;
+#[cfg(esp_idf_version_major = "5")]
+mod oob;
+mod pairing;
+mod security;
+
 lazy_static! {
     /// The GATT server singleton.
     pub static ref GLOBAL_GATT_SERVER: Mutex<GattServer> = Mutex::new(GattServer {
         profiles: Vec::new(),
         started: false,
+        storage_set: false,
         advertisement_parameters: esp_ble_adv_params_t {
             adv_int_min: 0x20,
             adv_int_max: 0x40,
             adv_type: esp_ble_adv_type_t_ADV_TYPE_IND,
-            own_addr_type: esp_ble_addr_type_t_BLE_ADDR_TYPE_RPA_PUBLIC,
+            own_addr_type: esp_ble_addr_type_t_BLE_ADDR_TYPE_PUBLIC,
             channel_map: esp_ble_adv_channel_t_ADV_CHNL_ALL,
             adv_filter_policy: esp_ble_adv_filter_t_ADV_FILTER_ALLOW_SCAN_ANY_CON_ANY,
             ..Default::default()
@@ -79,8 +120,30 @@ lazy_static! {
             flag: (ESP_BLE_ADV_FLAG_GEN_DISC | ESP_BLE_ADV_FLAG_BREDR_NOT_SPT) as u8,
         },
         advertisement_configured: false,
+        service_data: Vec::new(),
         device_name: "ESP32".to_string(),
-        active_connections: HashSet::new(),
+        address_mode: AddressMode::Public,
+        rpa_timeout: DEFAULT_RPA_TIMEOUT,
+        address_pending: false,
+        gap_mode: GapMode::GeneralDiscoverable,
+        gap_mode_fallback: GapMode::GeneralDiscoverable,
+        gap_mode_generation: 0,
+        accept_list: Vec::new(),
+        reconnection_policy: Arc::new(|_, _| ReconnectionPolicy::Restart),
+        advertising_generation: 0,
+        backoff_attempts: 0,
+        max_connections: 1,
+        advertising: false,
+        security: None,
+        pairing_callback: None,
+        pairing_timeout: DEFAULT_PAIRING_TIMEOUT,
+        pairing_outcome_callback: None,
+        authorization: None,
+        key_sizes: HashMap::new(),
+        #[cfg(esp_idf_version_major = "5")]
+        local_oob_callback: None,
+        #[cfg(esp_idf_version_major = "5")]
+        local_oob_data: None,
     });
 }
 
@@ -90,12 +153,35 @@ lazy_static! {
 pub struct GattServer {
     profiles: Vec<Arc<RwLock<Profile>>>,
     started: bool,
+    storage_set: bool,
     advertisement_parameters: esp_ble_adv_params_t,
     advertisement_data: esp_ble_adv_data_t,
     scan_response_data: esp_ble_adv_data_t,
     device_name: String,
     advertisement_configured: bool,
-    active_connections: HashSet<Connection>,
+    service_data: Vec<u8>,
+    address_mode: AddressMode,
+    rpa_timeout: Duration,
+    address_pending: bool,
+    gap_mode: GapMode,
+    gap_mode_fallback: GapMode,
+    gap_mode_generation: u32,
+    accept_list: Vec<BleAddress>,
+    reconnection_policy: Arc<reconnection::ReconnectionCallback>,
+    advertising_generation: u32,
+    backoff_attempts: u32,
+    max_connections: usize,
+    advertising: bool,
+    security: Option<SecurityConfig>,
+    pairing_callback: Option<Arc<pairing::PairingCallback>>,
+    pairing_timeout: Duration,
+    pairing_outcome_callback: Option<Arc<pairing::PairingOutcomeCallback>>,
+    authorization: Option<AuthorizationCallback>,
+    key_sizes: HashMap<[u8; 6], u8>,
+    #[cfg(esp_idf_version_major = "5")]
+    local_oob_callback: Option<Box<oob::LocalOobCallback>>,
+    #[cfg(esp_idf_version_major = "5")]
+    local_oob_data: Option<crate::utilities::ScOobData>,
 }
 
 unsafe impl Send for GattServer {}
@@ -113,7 +199,14 @@ impl GattServer {
         }
 
         self.started = true;
+        self.check_storage();
         Self::initialise_ble_stack();
+        remove_orphaned_cccd_values();
+        self.check_attribute_table();
+        self.configure_security();
+        self.configure_address();
+        self.configure_accept_list();
+        self.configure_gap_mode();
 
         // Registration of profiles, services, characteristics and descriptors.
         self.profiles.iter().for_each(|profile| {
@@ -177,6 +270,29 @@ impl GattServer {
         self
     }
 
+    /// Sets the service data to be advertised in GAP packets.
+    ///
+    /// The data must begin with the 16-bit service UUID, in little-endian order.
+    /// Unlike the other advertisement settings, the service data can be updated while the server is running.
+    pub fn advertise_service_data<T: Into<Vec<u8>>>(&mut self, data: T) -> &mut Self {
+        self.service_data = data.into();
+
+        if self.service_data.is_empty() {
+            self.advertisement_data.p_service_data = std::ptr::null_mut();
+        } else {
+            self.advertisement_data.p_service_data = self.service_data.as_mut_ptr();
+        }
+        self.advertisement_data.service_data_len = self.service_data.len() as u16;
+
+        if self.advertisement_configured {
+            unsafe {
+                esp_nofail!(esp_ble_gap_config_adv_data(&mut self.advertisement_data));
+            }
+        }
+
+        self
+    }
+
     /// Add a [`Profile`] to the GATT server.
     pub fn profile(&mut self, profile: Arc<RwLock<Profile>>) -> &mut Self {
         if self.started {
@@ -188,6 +304,29 @@ impl GattServer {
         self
     }
 
+    /// Sets the device name and configures the advertisement and scan response data.
+    ///
+    /// Does nothing if the advertisement is already configured, or if the local address is still being set.
+    pub(crate) fn configure_advertisement(&mut self) {
+        if self.advertisement_configured || self.address_pending {
+            return;
+        }
+
+        unsafe {
+            esp_nofail!(esp_ble_gap_set_device_name(
+                self.device_name.as_ptr().cast::<i8>()
+            ));
+
+            self.advertisement_configured = true;
+
+            // Advertisement data.
+            esp_nofail!(esp_ble_gap_config_adv_data(&mut self.advertisement_data));
+
+            // Scan response data.
+            esp_nofail!(esp_ble_gap_config_adv_data(&mut self.scan_response_data));
+        }
+    }
+
     pub(crate) fn get_profile(&self, interface: u8) -> Option<Arc<RwLock<Profile>>> {
         self.profiles
             .iter()
@@ -0,0 +1,258 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Condvar, Mutex},
+    time::Duration,
+};
+
+use esp_idf_sys::*;
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+
+use crate::{
+    gatt_server::GattServer,
+    utilities::{FlowControl, NotificationError, NotificationStats, OverflowPolicy},
+};
+
+lazy_static! {
+    /// The notification queue of every connection, by connection identifier.
+    ///
+    /// Notifications wait in the queue while the link is congested. The queues are kept apart
+    /// from the server, so that notifications can be sent from the callbacks.
+    static ref NOTIFICATION_QUEUES: Mutex<NotificationQueues> = Mutex::new(NotificationQueues::default());
+
+    /// Wakes up the applications waiting for room in a queue.
+    static ref QUEUE_DRAINED: Condvar = Condvar::new();
+}
+
+#[derive(Debug, Default)]
+struct NotificationQueues {
+    flow_control: FlowControl,
+    queues: HashMap<u16, NotificationQueue>,
+}
+
+impl NotificationQueues {
+    /// Returns whether the queue of a connection has no room left.
+    fn is_full(&self, conn_id: u16) -> bool {
+        self.queues
+            .get(&conn_id)
+            .is_some_and(|queue| queue.queue.len() >= self.flow_control.capacity)
+    }
+}
+
+#[derive(Debug, Default)]
+struct NotificationQueue {
+    queue: VecDeque<PendingNotification>,
+    stats: NotificationStats,
+    /// The number of notifications handed to the Bluetooth stack and not reported yet, by handle.
+    ///
+    /// The Bluetooth stack reports notifications and indications with the same event,
+    /// so this tells them apart.
+    unreported: HashMap<u16, usize>,
+}
+
+/// A notification waiting to be sent.
+#[derive(Debug)]
+pub(crate) struct PendingNotification {
+    pub(crate) conn_id: u16,
+    pub(crate) gatts_if: esp_gatt_if_t,
+    pub(crate) handle: u16,
+    pub(crate) value: Vec<u8>,
+}
+
+impl GattServer {
+    /// Sets the [`FlowControl`] of notifications. See [`FlowControl::new`] for the default.
+    pub fn flow_control(&mut self, flow_control: FlowControl) -> &mut Self {
+        NOTIFICATION_QUEUES.lock().unwrap().flow_control = flow_control;
+        self
+    }
+}
+
+/// Creates the notification queue of a new connection.
+pub(crate) fn open_notification_queue(conn_id: u16) {
+    NOTIFICATION_QUEUES
+        .lock()
+        .unwrap()
+        .queues
+        .insert(conn_id, NotificationQueue::default());
+}
+
+/// Drops the notification queue of a disconnected client, and wakes up its waiting senders.
+pub(crate) fn close_notification_queue(conn_id: u16) {
+    if let Some(queue) = NOTIFICATION_QUEUES.lock().unwrap().queues.remove(&conn_id) {
+        if !queue.queue.is_empty() {
+            debug!(
+                "Dropping {} queued notifications of connection {}.",
+                queue.queue.len(),
+                conn_id
+            );
+        }
+    }
+
+    QUEUE_DRAINED.notify_all();
+}
+
+/// Sends a notification, or queues it while the link is congested or other notifications are waiting.
+///
+/// With the [`OverflowPolicy::Block`] policy, a full queue is reported to the caller if `may_retry` is set,
+/// so that it can wait for room without holding any lock. Otherwise the new notification is dropped,
+/// and the queued ones are kept.
+pub(crate) fn send_notification(
+    notification: PendingNotification,
+    may_retry: bool,
+) -> Result<(), NotificationError> {
+    let mut queues = NOTIFICATION_QUEUES.lock().unwrap();
+    let flow_control = queues.flow_control;
+    let Some(queue) = queues.queues.get_mut(&notification.conn_id) else {
+        return Err(NotificationError::NotConnected);
+    };
+
+    if !queue.stats.congested && queue.queue.is_empty() {
+        return transmit(queue, notification);
+    }
+
+    if queue.queue.len() < flow_control.capacity {
+        queue.queue.push_back(notification);
+        queue.stats.queued = queue.queue.len();
+        return Ok(());
+    }
+
+    match flow_control.policy {
+        OverflowPolicy::Block if may_retry => Err(NotificationError::QueueFull),
+        OverflowPolicy::DropNewest | OverflowPolicy::Block => {
+            queue.stats.dropped += 1;
+            Err(NotificationError::Dropped)
+        }
+        OverflowPolicy::DropOldest => {
+            queue.queue.pop_front();
+            queue.queue.push_back(notification);
+            queue.stats.dropped += 1;
+            Ok(())
+        }
+    }
+}
+
+/// Waits until the notification queue of a connection has room, or the timeout expires.
+///
+/// Returns `false` if the queue is still full, or if the client disconnected.
+pub(crate) fn wait_for_room(conn_id: u16, timeout: Duration) -> bool {
+    let queues = NOTIFICATION_QUEUES.lock().unwrap();
+    let (queues, _) = QUEUE_DRAINED
+        .wait_timeout_while(queues, timeout, |queues| queues.is_full(conn_id))
+        .unwrap();
+
+    queues.queues.contains_key(&conn_id) && !queues.is_full(conn_id)
+}
+
+/// Pauses or resumes the notifications of a connection.
+pub(crate) fn set_congested(conn_id: u16, congested: bool) {
+    let mut queues = NOTIFICATION_QUEUES.lock().unwrap();
+    let Some(queue) = queues.queues.get_mut(&conn_id) else {
+        return;
+    };
+
+    queue.stats.congested = congested;
+    if congested {
+        return;
+    }
+
+    if !queue.queue.is_empty() {
+        info!(
+            "Resuming {} queued notifications to connection {}.",
+            queue.queue.len(),
+            conn_id
+        );
+    }
+
+    send_next(queue);
+}
+
+/// Hands the next queued notification to the Bluetooth stack.
+///
+/// The queue is drained one notification at a time, each one after the report of the previous one,
+/// so that the Bluetooth stack can report a new congestion before the rest of the queue is sent.
+fn send_next(queue: &mut NotificationQueue) {
+    let mut sent = false;
+
+    while let Some(notification) = queue.queue.pop_front() {
+        // Failures are logged and counted, and the next notification is tried.
+        sent = transmit(queue, notification).is_ok();
+        if sent {
+            break;
+        }
+    }
+    queue.stats.queued = queue.queue.len();
+
+    if sent || queue.queue.is_empty() {
+        QUEUE_DRAINED.notify_all();
+    }
+}
+
+/// Handles the report of a notification sent by the Bluetooth stack, and counts it if it failed.
+///
+/// Returns `false` if no notification was pending on the attribute, so the event reports an indication.
+pub(crate) fn on_notification_sent(conn_id: u16, handle: u16, status: esp_gatt_status_t) -> bool {
+    let mut queues = NOTIFICATION_QUEUES.lock().unwrap();
+    let Some(queue) = queues.queues.get_mut(&conn_id) else {
+        return false;
+    };
+
+    let Some(unreported) = queue
+        .unreported
+        .get_mut(&handle)
+        .filter(|count| **count > 0)
+    else {
+        return false;
+    };
+    *unreported -= 1;
+
+    if status != esp_gatt_status_t_ESP_GATT_OK {
+        warn!("Notification failed, error code: {:04x}.", status);
+        queue.stats.failed += 1;
+    }
+
+    if !queue.stats.congested {
+        send_next(queue);
+    }
+
+    true
+}
+
+/// Returns the notification counters of a connection.
+pub(crate) fn notification_stats(conn_id: u16) -> Option<NotificationStats> {
+    NOTIFICATION_QUEUES
+        .lock()
+        .unwrap()
+        .queues
+        .get(&conn_id)
+        .map(|queue| queue.stats)
+}
+
+/// Hands a notification to the Bluetooth stack.
+fn transmit(
+    queue: &mut NotificationQueue,
+    mut notification: PendingNotification,
+) -> Result<(), NotificationError> {
+    let result = unsafe {
+        esp!(esp_ble_gatts_send_indicate(
+            notification.gatts_if,
+            notification.conn_id,
+            notification.handle,
+            notification.value.len() as u16,
+            notification.value.as_mut_ptr(),
+            false
+        ))
+    };
+
+    match result {
+        Ok(()) => {
+            queue.stats.sent += 1;
+            *queue.unreported.entry(notification.handle).or_default() += 1;
+            Ok(())
+        }
+        Err(error) => {
+            warn!("Failed to notify value change: {}.", error);
+            queue.stats.failed += 1;
+            Err(NotificationError::Stack(error))
+        }
+    }
+}
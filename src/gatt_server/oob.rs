@@ -0,0 +1,73 @@
+use esp_idf_sys::*;
+use log::{info, warn};
+
+use crate::{
+    gatt_server::GattServer,
+    utilities::{PairingEvent, PendingReply, ScOobData, ScOobRequest},
+};
+
+pub(crate) type LocalOobCallback = dyn FnOnce(ScOobData) + Send;
+
+impl GattServer {
+    /// Generates the local LE Secure Connections out-of-band data,
+    /// so that it can be exported to the peer, for example through an NFC tag.
+    ///
+    /// The callback receives the data once the Bluetooth stack has generated it.
+    /// The data is also available afterwards through [`GattServer::local_sc_oob_data`].
+    ///
+    /// # Notes
+    ///
+    /// Only available with ESP-IDF 5 and later. The server must be started. The callback will be called from the Bluetooth stack's context,
+    /// so it must not block.
+    pub fn generate_sc_oob_data<C: FnOnce(ScOobData) + Send + 'static>(
+        &mut self,
+        callback: C,
+    ) -> &mut Self {
+        if !self.started {
+            warn!("Cannot generate OOB data before the server has started.");
+            return self;
+        }
+
+        self.local_oob_callback = Some(Box::new(callback));
+
+        let result = unsafe { esp!(esp_ble_create_sc_oob_data()) };
+        if let Err(error) = result {
+            warn!("Cannot generate OOB data: {}.", error);
+            self.local_oob_callback = None;
+        }
+
+        self
+    }
+
+    /// Returns the last local LE Secure Connections out-of-band data generated by [`GattServer::generate_sc_oob_data`].
+    #[must_use]
+    pub const fn local_sc_oob_data(&self) -> Option<ScOobData> {
+        self.local_oob_data
+    }
+
+    pub(crate) fn on_local_oob_data(&mut self, param: esp_ble_local_oob_data_t) {
+        info!("Local Secure Connections OOB data generated.");
+
+        let data = ScOobData {
+            confirm: param.oob_c,
+            random: param.oob_r,
+        };
+
+        self.local_oob_data = Some(data);
+        if let Some(callback) = self.local_oob_callback.take() {
+            callback(data);
+        }
+    }
+
+    pub(crate) fn on_sc_oob_request(&self, param: esp_ble_sec_req_t) {
+        let reply = self.pending_reply(param.bd_addr, PendingReply::sc_oob_reject);
+
+        match &self.pairing_callback {
+            Some(callback) => callback(PairingEvent::ScOobRequest(ScOobRequest(reply))),
+            None => {
+                warn!("No pairing callback set. Rejecting Secure Connections OOB request.");
+                reply.sc_oob_reject();
+            }
+        }
+    }
+}
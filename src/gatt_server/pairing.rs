@@ -0,0 +1,149 @@
+use std::{sync::Arc, time::Duration};
+
+use esp_idf_sys::*;
+use log::{info, warn};
+
+use crate::{
+    gatt_server::GattServer,
+    utilities::{
+        BleAddress, ConfirmationRequest, OobRequest, PairingEvent, PairingFailure, PasskeyRequest,
+        PendingReply,
+    },
+};
+
+pub(crate) type PairingCallback = dyn Fn(PairingEvent) + Send + Sync;
+pub(crate) type PairingOutcomeCallback =
+    dyn Fn(BleAddress, Result<(), PairingFailure>) + Send + Sync;
+
+impl GattServer {
+    /// Sets a callback that handles the interactive steps of pairing:
+    /// displaying a passkey, entering a passkey, numeric comparison and out-of-band data.
+    ///
+    /// Requests can be answered later from another thread, but are rejected
+    /// if they are not answered within the pairing timeout.
+    /// Without a callback, all requests are rejected.
+    ///
+    /// LE Secure Connections out-of-band requests are only reported with ESP-IDF 5 and later.
+    /// With ESP-IDF 4, out-of-band pairing is limited to the legacy temporary key.
+    ///
+    /// # Notes
+    ///
+    /// The callback will be called from the Bluetooth stack's context, so it must not block.
+    pub fn on_pairing<C: Fn(PairingEvent) + Send + Sync + 'static>(
+        &mut self,
+        callback: C,
+    ) -> &mut Self {
+        self.pairing_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sets how long the application has to answer a pairing request. The default is 20 seconds.
+    ///
+    /// The peer gives up after 30 seconds, so the timeout should stay below that.
+    pub fn pairing_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.pairing_timeout = timeout;
+        self
+    }
+
+    /// Sets a callback that receives the outcome of every pairing procedure.
+    ///
+    /// # Notes
+    ///
+    /// The callback will be called from the Bluetooth stack's context, so it must not block.
+    pub fn on_pairing_complete<
+        C: Fn(BleAddress, Result<(), PairingFailure>) + Send + Sync + 'static,
+    >(
+        &mut self,
+        callback: C,
+    ) -> &mut Self {
+        self.pairing_outcome_callback = Some(Arc::new(callback));
+        self
+    }
+
+    pub(crate) fn on_passkey_notification(&self, param: esp_ble_sec_key_notif_t) {
+        let peer = BleAddress::public(param.bd_addr);
+        info!("Passkey for {}: {:06}.", peer, param.passkey);
+
+        if let Some(callback) = &self.pairing_callback {
+            callback(PairingEvent::DisplayPasskey {
+                peer,
+                passkey: param.passkey,
+            });
+        }
+    }
+
+    pub(crate) fn on_passkey_request(&self, param: esp_ble_sec_req_t) {
+        let reply = self.pending_reply(param.bd_addr, |reply| reply.passkey_reply(false, 0));
+
+        match &self.pairing_callback {
+            Some(callback) => callback(PairingEvent::PasskeyRequest(PasskeyRequest(reply))),
+            None => {
+                warn!("No pairing callback set. Rejecting passkey request.");
+                reply.passkey_reply(false, 0);
+            }
+        }
+    }
+
+    pub(crate) fn on_numeric_comparison(&self, param: esp_ble_sec_key_notif_t) {
+        let reply = self.pending_reply(param.bd_addr, |reply| reply.confirm_reply(false));
+
+        match &self.pairing_callback {
+            Some(callback) => callback(PairingEvent::NumericComparison(ConfirmationRequest(
+                reply,
+                param.passkey,
+            ))),
+            None => {
+                warn!("No pairing callback set. Rejecting numeric comparison.");
+                reply.confirm_reply(false);
+            }
+        }
+    }
+
+    pub(crate) fn on_oob_request(&self, param: esp_ble_sec_req_t) {
+        let reply = self.pending_reply(param.bd_addr, |reply| reply.oob_reply(None));
+
+        match &self.pairing_callback {
+            Some(callback) => callback(PairingEvent::OobRequest(OobRequest(reply))),
+            None => {
+                warn!("No pairing callback set. Rejecting OOB request.");
+                reply.oob_reply(None);
+            }
+        }
+    }
+
+    pub(crate) fn on_pairing_outcome(&self, peer: BleAddress, param: esp_ble_auth_cmpl_t) {
+        if let Some(callback) = &self.pairing_outcome_callback {
+            let outcome = if param.success {
+                Ok(())
+            } else {
+                Err(PairingFailure::from(param.fail_reason))
+            };
+
+            callback(peer, outcome);
+        }
+    }
+
+    /// Creates the reply handle of a pairing request, and rejects it with `reject`
+    /// if it is not answered within the pairing timeout.
+    pub(crate) fn pending_reply<F: FnOnce(&PendingReply) + Send + 'static>(
+        &self,
+        address: [u8; 6],
+        reject: F,
+    ) -> PendingReply {
+        let peer = BleAddress::public(address);
+        let reply = PendingReply::new(peer);
+        let timeout = self.pairing_timeout;
+        let watchdog = reply.clone();
+
+        std::thread::spawn(move || {
+            std::thread::sleep(timeout);
+
+            if !watchdog.is_answered() {
+                warn!("Pairing request from {} timed out.", peer);
+                reject(&watchdog);
+            }
+        });
+
+        reply
+    }
+}
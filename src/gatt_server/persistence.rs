@@ -0,0 +1,140 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use log::{debug, warn};
+
+use crate::gatt_server::{storage::with_storage, GattServer};
+
+/// How long a persistent value must stay unchanged before it is written to the storage.
+pub(crate) const PERSIST_DEBOUNCE: Duration = Duration::from_secs(1);
+
+lazy_static! {
+    /// The persistent values waiting to be written, by storage key.
+    ///
+    /// Writes are debounced, so that a client writing in a loop does not wear out the flash.
+    static ref PENDING_WRITES: Mutex<HashMap<String, PendingWrite>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug)]
+struct PendingWrite {
+    value: Vec<u8>,
+    deadline: Instant,
+}
+
+impl GattServer {
+    /// Writes the persistent values that are waiting for the end of their debounce delay to the storage.
+    ///
+    /// This is done when a client disconnects. Call this before a reset or a deep sleep,
+    /// so that the last values written by the clients are not lost.
+    pub fn flush_persistent_values() {
+        let pending: Vec<(String, PendingWrite)> = PENDING_WRITES.lock().unwrap().drain().collect();
+
+        for (key, write) in pending {
+            write_value(&key, &write.value);
+        }
+    }
+}
+
+/// Hashes `bytes` with FNV-1a, which is stable across builds, to make short storage keys and fingerprints.
+pub(crate) fn fnv1a<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> u64 {
+    bytes
+        .into_iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Returns the storage key of a persistent value.
+pub(crate) fn value_key(key: &str) -> String {
+    format!("v{key}")
+}
+
+/// Reads a persistent value, unless it was stored under another version.
+pub(crate) fn load_value(key: &str, version: u16) -> Option<Vec<u8>> {
+    let stored = match with_storage(|storage| storage.get(key)) {
+        Ok(stored) => stored?,
+        Err(error) => {
+            warn!("Cannot read persistent value at key {}: {}.", key, error);
+            return None;
+        }
+    };
+
+    // The value is stored after its version.
+    if stored.get(..2) != Some(&version.to_le_bytes()[..]) {
+        debug!(
+            "Discarding persistent value at key {}, stored under another version.",
+            key
+        );
+        return None;
+    }
+
+    debug!(
+        "Loaded persistent value {:02X?} at key {}.",
+        &stored[2..],
+        key
+    );
+    Some(stored[2..].to_vec())
+}
+
+/// Writes a persistent value once it has not changed for [`PERSIST_DEBOUNCE`].
+pub(crate) fn store_value(key: &str, version: u16, value: &[u8]) {
+    let mut stored = version.to_le_bytes().to_vec();
+    stored.extend_from_slice(value);
+
+    let deadline = Instant::now() + PERSIST_DEBOUNCE;
+
+    {
+        let mut pending = PENDING_WRITES.lock().unwrap();
+        if let Some(write) = pending.get_mut(key) {
+            write.value = stored;
+            write.deadline = deadline;
+            return;
+        }
+
+        pending.insert(
+            key.to_string(),
+            PendingWrite {
+                value: stored,
+                deadline,
+            },
+        );
+    }
+
+    let key = key.to_string();
+    std::thread::spawn(move || {
+        let mut deadline = deadline;
+
+        loop {
+            std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+
+            let mut pending = PENDING_WRITES.lock().unwrap();
+            let Some(write) = pending.get(&key) else {
+                return;
+            };
+
+            // The value changed in the meantime.
+            if write.deadline > Instant::now() {
+                deadline = write.deadline;
+                continue;
+            }
+
+            let write = pending.remove(&key).unwrap();
+            drop(pending);
+
+            write_value(&key, &write.value);
+            return;
+        }
+    });
+}
+
+/// Writes a persistent value, along with its version, to the storage.
+fn write_value(key: &str, stored: &[u8]) {
+    match with_storage(|storage| storage.set(key, stored)) {
+        Ok(()) => debug!("Stored persistent value at key {}.", key),
+        Err(error) => warn!("Cannot store persistent value at key {}: {}.", key, error),
+    }
+}
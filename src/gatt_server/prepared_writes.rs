@@ -0,0 +1,100 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use esp_idf_sys::*;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// The prepared writes waiting to be executed, by interface and connection.
+    ///
+    /// The Bluetooth stack only queues the prepared writes of the attributes it answers itself.
+    /// The others are queued here, and applied once the client executes them.
+    static ref PREPARED_WRITES: Mutex<HashMap<(esp_gatt_if_t, u16), Vec<PreparedWrite>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// A part of a long write.
+#[derive(Debug)]
+struct PreparedWrite {
+    handle: u16,
+    offset: u16,
+    value: Vec<u8>,
+}
+
+/// Queues a part of a long write until the client executes it.
+pub(crate) fn prepare_write(
+    gatts_if: esp_gatt_if_t,
+    conn_id: u16,
+    handle: u16,
+    offset: u16,
+    value: &[u8],
+) {
+    PREPARED_WRITES
+        .lock()
+        .unwrap()
+        .entry((gatts_if, conn_id))
+        .or_default()
+        .push(PreparedWrite {
+            handle,
+            offset,
+            value: value.to_vec(),
+        });
+}
+
+/// Remembers that a prepared write was denied, so that the execution is still answered.
+pub(crate) fn deny_prepared_write(gatts_if: esp_gatt_if_t, conn_id: u16) {
+    PREPARED_WRITES
+        .lock()
+        .unwrap()
+        .entry((gatts_if, conn_id))
+        .or_default();
+}
+
+/// Removes the prepared writes of a connection, and assembles the written value of each attribute.
+///
+/// Returns `None` if the application did not receive any prepared write on this interface.
+/// A value is replaced by the ATT error to answer with if its parts do not follow each other.
+#[allow(clippy::type_complexity)]
+pub(crate) fn take_prepared_writes(
+    gatts_if: esp_gatt_if_t,
+    conn_id: u16,
+) -> Option<Vec<(u16, Result<Vec<u8>, esp_gatt_status_t>)>> {
+    let writes = PREPARED_WRITES
+        .lock()
+        .unwrap()
+        .remove(&(gatts_if, conn_id))?;
+
+    let mut values: Vec<(u16, Result<Vec<u8>, esp_gatt_status_t>)> = Vec::new();
+    for write in writes {
+        let index = if let Some(index) = values
+            .iter()
+            .position(|(handle, _)| *handle == write.handle)
+        {
+            index
+        } else {
+            values.push((write.handle, Ok(Vec::new())));
+            values.len() - 1
+        };
+
+        let (_, result) = &mut values[index];
+        if let Ok(value) = result {
+            let offset = usize::from(write.offset);
+            if offset > value.len() {
+                *result = Err(esp_gatt_status_t_ESP_GATT_INVALID_OFFSET);
+                continue;
+            }
+
+            value.truncate(offset);
+            value.extend_from_slice(&write.value);
+        }
+    }
+
+    Some(values)
+}
+
+/// Drops the prepared writes of a disconnected client.
+pub(crate) fn drop_prepared_writes(conn_id: u16) {
+    PREPARED_WRITES
+        .lock()
+        .unwrap()
+        .retain(|(_, id), _| *id != conn_id);
+}
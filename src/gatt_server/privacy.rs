@@ -0,0 +1,271 @@
+use std::time::Duration;
+
+use aes::{
+    cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit},
+    Aes128,
+};
+use esp_idf_sys::*;
+use log::{debug, info, warn};
+
+use crate::{
+    gatt_server::GattServer,
+    utilities::{AddressMode, BleAddress},
+};
+
+impl GattServer {
+    /// Sets the address that the device uses over the air.
+    ///
+    /// The address mode must be set before starting the server.
+    pub fn address_mode(&mut self, mode: AddressMode) -> &mut Self {
+        if self.started {
+            warn!("Cannot change the address mode after the server has started.");
+            return self;
+        }
+
+        if let AddressMode::StaticRandom(address) = mode {
+            if !BleAddress::random(address).is_static_random() {
+                warn!(
+                    "{} is not a valid static random address. Ignoring address mode.",
+                    BleAddress::random(address)
+                );
+                return self;
+            }
+        }
+
+        self.address_mode = mode;
+        self.advertisement_parameters.own_addr_type = match mode {
+            AddressMode::Public => esp_ble_addr_type_t_BLE_ADDR_TYPE_PUBLIC,
+            AddressMode::StaticRandom(_) => esp_ble_addr_type_t_BLE_ADDR_TYPE_RANDOM,
+            AddressMode::ResolvablePrivate => esp_ble_addr_type_t_BLE_ADDR_TYPE_RPA_PUBLIC,
+        };
+
+        self
+    }
+
+    /// Sets how long a resolvable private address is used before a new one is generated.
+    ///
+    /// The timeout must be between 1 second and 1 hour. The default is 15 minutes.
+    /// It only applies to the [`AddressMode::ResolvablePrivate`] mode, and must be set before starting the server.
+    pub fn rpa_timeout(&mut self, timeout: Duration) -> &mut Self {
+        if self.started {
+            warn!("Cannot change the RPA timeout after the server has started.");
+            return self;
+        }
+
+        if !(1..=3600).contains(&timeout.as_secs()) {
+            warn!(
+                "RPA timeout of {:?} is out of range. Ignoring RPA timeout.",
+                timeout
+            );
+            return self;
+        }
+
+        self.rpa_timeout = timeout;
+        self
+    }
+
+    /// Returns the identity address of the device.
+    ///
+    /// This is the address that bonded peers learn during pairing, and it does not change
+    /// when a resolvable private address is in use.
+    #[must_use]
+    pub fn identity_address(&self) -> BleAddress {
+        if let AddressMode::StaticRandom(address) = self.address_mode {
+            return BleAddress::random(address);
+        }
+
+        let mut address = [0u8; 6];
+        unsafe {
+            esp_nofail!(esp_read_mac(
+                address.as_mut_ptr(),
+                esp_mac_type_t_ESP_MAC_BT
+            ));
+        }
+
+        BleAddress::public(address)
+    }
+
+    /// Returns the address that the device is currently using over the air.
+    ///
+    /// Returns `None` if the Bluetooth stack is not running.
+    #[must_use]
+    pub fn local_address(&self) -> Option<BleAddress> {
+        if !self.started {
+            return None;
+        }
+
+        let mut address = [0u8; 6];
+        let mut address_type = 0u8;
+        let result = unsafe {
+            esp!(esp_ble_gap_get_local_used_addr(
+                address.as_mut_ptr(),
+                &mut address_type
+            ))
+        };
+
+        result
+            .ok()
+            .map(|_| BleAddress::from_esp(address, address_type.into()))
+    }
+
+    /// Returns the identity addresses of the bonded peers whose identity resolving key is known.
+    ///
+    /// Bluedroid loads these peers into the controller's resolving list,
+    /// so that they are recognised when connecting with a resolvable private address.
+    #[must_use]
+    pub fn resolving_list() -> Vec<BleAddress> {
+        Self::bond_device_list()
+            .iter()
+            .filter(|device| u32::from(device.bond_key.key_mask) & ESP_BLE_ID_KEY_MASK != 0)
+            .map(Self::bond_identity)
+            .collect()
+    }
+
+    /// Resolves a resolvable private address against the identity resolving keys of the bonded peers.
+    ///
+    /// Returns the identity address of the matching peer. Other kinds of addresses are returned unchanged
+    /// if they belong to a bonded peer.
+    #[must_use]
+    pub fn resolve_address(address: BleAddress) -> Option<BleAddress> {
+        let bytes = address.bytes();
+
+        Self::bond_device_list().iter().find_map(|device| {
+            // Bonds without an identity resolving key are known by the address they bonded with.
+            let identity = Self::bond_identity(device);
+            let has_irk = u32::from(device.bond_key.key_mask) & ESP_BLE_ID_KEY_MASK != 0;
+
+            // The connection events do not carry the address type, so only the address bits are checked.
+            // A public address can have the same top bits, so it is compared when the hash does not match.
+            let resolved = bytes[0] >> 6 == 0b01
+                && has_irk
+                && ah(&device.bond_key.pid_key.irk, [bytes[0], bytes[1], bytes[2]])
+                    == [bytes[3], bytes[4], bytes[5]];
+
+            (resolved || device.bd_addr == bytes || (has_irk && identity == address))
+                .then_some(identity)
+        })
+    }
+
+    /// Reads the list of bonded devices from the Bluetooth stack.
+    pub(crate) fn bond_device_list() -> Vec<esp_ble_bond_dev_t> {
+        let mut count = unsafe { esp_ble_get_bond_device_num() };
+        let Ok(capacity) = usize::try_from(count) else {
+            return Vec::new();
+        };
+
+        let mut list = vec![esp_ble_bond_dev_t::default(); capacity];
+        let result = unsafe { esp!(esp_ble_get_bond_device_list(&mut count, list.as_mut_ptr())) };
+
+        if let Err(error) = result {
+            warn!("Cannot read the bonded device list: {}.", error);
+            return Vec::new();
+        }
+
+        list.truncate(usize::try_from(count).unwrap_or_default());
+        list
+    }
+
+    /// Applies the configured address mode.
+    ///
+    /// The advertisement is configured once the stack has confirmed the new address.
+    pub(crate) fn configure_address(&mut self) {
+        match self.address_mode {
+            AddressMode::Public => {}
+            AddressMode::StaticRandom(mut address) => {
+                info!(
+                    "Setting static random address {}.",
+                    BleAddress::random(address)
+                );
+                self.address_pending = true;
+                unsafe {
+                    esp_nofail!(esp_ble_gap_set_rand_addr(address.as_mut_ptr()));
+                }
+            }
+            AddressMode::ResolvablePrivate => {
+                info!("Enabling local privacy.");
+
+                #[cfg(esp_idf_version = "5.1")]
+                unsafe {
+                    esp_nofail!(esp_ble_gap_set_resolvable_private_address_timeout(
+                        self.rpa_timeout.as_secs() as u16
+                    ));
+                }
+
+                #[cfg(not(esp_idf_version = "5.1"))]
+                if self.rpa_timeout != crate::utilities::DEFAULT_RPA_TIMEOUT {
+                    warn!("Setting the RPA timeout requires ESP-IDF 5.1. Using the controller default.");
+                }
+
+                self.address_pending = true;
+                unsafe {
+                    esp_nofail!(esp_ble_gap_config_local_privacy(true));
+                }
+            }
+        }
+    }
+
+    pub(crate) fn on_address_configured(&mut self, status: esp_bt_status_t) {
+        if status == esp_bt_status_t_ESP_BT_STATUS_SUCCESS {
+            debug!("Local address configured.");
+        } else {
+            warn!("Local address configuration failed: {:?}.", status);
+        }
+
+        self.address_pending = false;
+
+        // If a profile was registered while waiting, the advertisement is still to be configured.
+        let registered = self
+            .profiles
+            .iter()
+            .any(|profile| profile.read().unwrap().interface.is_some());
+
+        if registered {
+            self.configure_advertisement();
+        }
+    }
+}
+
+/// The random address hash function `ah`, as defined in the Core specification, Vol. 3, Part H, 2.2.2.
+///
+/// Bluedroid stores keys least significant byte first, while AES works on the most significant byte first.
+fn ah(irk: &[u8; 16], prand: [u8; 3]) -> [u8; 3] {
+    let mut key = *irk;
+    key.reverse();
+
+    let mut block = GenericArray::from([0u8; 16]);
+    block[13..].copy_from_slice(&prand);
+    Aes128::new(GenericArray::from_slice(&key)).encrypt_block(&mut block);
+
+    [block[13], block[14], block[15]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The identity resolving key of the sample data in the Core specification, Vol 3, Part H, D.7.
+    const IRK: [u8; 16] = [
+        0xEC, 0x02, 0x34, 0xA3, 0x57, 0xC8, 0xAD, 0x05, 0x34, 0x10, 0x10, 0xA6, 0x0A, 0x39, 0x7D,
+        0x9B,
+    ];
+
+    fn stored_irk() -> [u8; 16] {
+        // Bluedroid stores keys least significant byte first.
+        let mut irk = IRK;
+        irk.reverse();
+        irk
+    }
+
+    #[test]
+    fn hashes_the_specification_sample() {
+        assert_eq!(ah(&stored_irk(), [0x70, 0x81, 0x94]), [0x0D, 0xFB, 0xAA]);
+    }
+
+    #[test]
+    fn hash_depends_on_the_key() {
+        let mut irk = stored_irk();
+        irk[0] ^= 1;
+
+        assert_ne!(ah(&irk, [0x70, 0x81, 0x94]), [0x0D, 0xFB, 0xAA]);
+    }
+}
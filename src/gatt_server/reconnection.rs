@@ -0,0 +1,142 @@
+use std::{sync::Arc, time::Duration};
+
+use esp_idf_sys::*;
+use log::{debug, info, warn};
+
+use crate::{
+    gatt_server::{GattServer, GLOBAL_GATT_SERVER},
+    utilities::{BleAddress, Connection, DisconnectReason, ReconnectionPolicy},
+};
+
+pub(crate) type ReconnectionCallback =
+    dyn Fn(DisconnectReason, BleAddress) -> ReconnectionPolicy + Send + Sync;
+
+impl GattServer {
+    /// Sets the [`ReconnectionPolicy`] applied every time a client disconnects.
+    ///
+    /// The default policy is [`ReconnectionPolicy::Restart`].
+    pub fn reconnection_policy(&mut self, policy: ReconnectionPolicy) -> &mut Self {
+        self.reconnection_policy = Arc::new(move |_, _| policy);
+        self
+    }
+
+    /// Sets a callback that chooses the [`ReconnectionPolicy`] when a client disconnects.
+    ///
+    /// The callback receives the reason of the disconnection and the address of the peer:
+    /// its identity address if it is bonded, or the address seen over the air otherwise.
+    ///
+    /// # Notes
+    ///
+    /// The callback will be called from the Bluetooth stack's context, so it must not block.
+    /// It runs while the [`GLOBAL_GATT_SERVER`] is locked, so it must not use the server.
+    pub fn on_disconnect_policy<
+        C: Fn(DisconnectReason, BleAddress) -> ReconnectionPolicy + Send + Sync + 'static,
+    >(
+        &mut self,
+        callback: C,
+    ) -> &mut Self {
+        self.reconnection_policy = Arc::new(callback);
+        self
+    }
+
+    /// Decides what to do with the advertisement after a client disconnected.
+    pub(crate) fn apply_reconnection_policy(
+        &mut self,
+        connection: Connection,
+        reason: DisconnectReason,
+    ) {
+        let identity = Self::resolve_address(BleAddress::public(connection.remote_bda));
+        let peer = identity.unwrap_or_else(|| BleAddress::public(connection.remote_bda));
+        let policy = (self.reconnection_policy)(reason, peer);
+
+        debug!(
+            "Applying reconnection policy {:?} after disconnection ({:?}).",
+            policy, reason
+        );
+
+        // Cancel any pending advertisement restart.
+        self.advertising_generation = self.advertising_generation.wrapping_add(1);
+
+        match policy {
+            ReconnectionPolicy::Stop => {
+                info!("Not restarting advertisement.");
+            }
+            ReconnectionPolicy::Restart => self.start_advertising(),
+            ReconnectionPolicy::Directed { duration } => {
+                // The disconnection event does not carry the address type, so only bonded peers can be targeted.
+                let Some(peer) = identity else {
+                    warn!(
+                        "Cannot direct the advertisement to {}, whose address type is unknown.",
+                        connection
+                    );
+                    self.start_advertising();
+                    return;
+                };
+
+                info!(
+                    "Advertising to {} for {:?} before advertising to everyone.",
+                    peer, duration
+                );
+
+                let mut parameters = self.advertisement_parameters;
+                parameters.adv_type = esp_ble_adv_type_t_ADV_TYPE_DIRECT_IND_LOW;
+                parameters.peer_addr = peer.bytes();
+                parameters.peer_addr_type = peer.esp_address_type();
+
+                let result = unsafe { esp!(esp_ble_gap_start_advertising(&mut parameters)) };
+                if let Err(error) = result {
+                    warn!("Cannot start directed advertising: {}.", error);
+                }
+
+                self.schedule_advertising(duration, true);
+            }
+            ReconnectionPolicy::Backoff { initial, max } => {
+                if connection.connected_at.elapsed() > max {
+                    self.backoff_attempts = 0;
+                }
+
+                let delay = initial
+                    .saturating_mul(2u32.saturating_pow(self.backoff_attempts))
+                    .min(max);
+                self.backoff_attempts = self.backoff_attempts.saturating_add(1);
+
+                info!("Restarting advertisement in {:?}.", delay);
+                self.schedule_advertising(delay, false);
+            }
+            ReconnectionPolicy::WhileBelow(limit) => {
+                if Connection::count() < limit {
+                    self.start_advertising();
+                } else {
+                    info!(
+                        "{} clients connected. Not restarting advertisement.",
+                        Connection::count()
+                    );
+                }
+            }
+        }
+    }
+
+    /// Restarts the advertisement with the normal parameters after a delay,
+    /// unless a client connects or another policy is applied in the meantime.
+    fn schedule_advertising(&self, delay: Duration, stop_first: bool) {
+        let generation = self.advertising_generation;
+
+        std::thread::spawn(move || {
+            std::thread::sleep(delay);
+
+            let mut server = GLOBAL_GATT_SERVER.lock().unwrap();
+            if server.advertising_generation != generation {
+                return;
+            }
+
+            if stop_first {
+                unsafe {
+                    esp_nofail!(esp_ble_gap_stop_advertising());
+                }
+                server.advertising = false;
+            }
+
+            server.start_advertising();
+        });
+    }
+}
@@ -0,0 +1,245 @@
+use std::ffi::c_void;
+
+use esp_idf_sys::*;
+use log::{debug, info, warn};
+
+use crate::{
+    gatt_server::{
+        custom_attributes::{persist_cccd_values, remove_orphaned_cccd_values},
+        GattServer,
+    },
+    utilities::{BleAddress, Connection, LinkSecurity, SecurityConfig},
+};
+
+impl GattServer {
+    /// Sets the security and pairing configuration of the device.
+    ///
+    /// Without a configuration, the Bluetooth stack defaults are used,
+    /// and security requests from peers are rejected.
+    pub fn security(&mut self, config: SecurityConfig) -> &mut Self {
+        self.security = Some(config);
+
+        if self.started {
+            self.configure_security();
+        }
+
+        self
+    }
+
+    /// Applies the security configuration to the Bluetooth stack.
+    pub(crate) fn configure_security(&self) {
+        let Some(config) = self.security else {
+            return;
+        };
+
+        debug!("Applying security configuration: {:?}.", config);
+
+        let mut auth_req = config.auth_req();
+        let mut io_capabilities: esp_ble_io_cap_t = config.io_capabilities.into();
+        let mut initiator_keys: u8 = config.initiator_keys.into();
+        let mut responder_keys: u8 = config.responder_keys.into();
+        let mut min_key_size = config.min_key_size;
+        let mut max_key_size = config.max_key_size;
+        let mut oob_support = if config.oob {
+            ESP_BLE_OOB_ENABLE as u8
+        } else {
+            ESP_BLE_OOB_DISABLE as u8
+        };
+        let mut only_accept_specified = if config.secure_connections_only {
+            ESP_BLE_ONLY_ACCEPT_SPECIFIED_AUTH_ENABLE as u8
+        } else {
+            ESP_BLE_ONLY_ACCEPT_SPECIFIED_AUTH_DISABLE as u8
+        };
+
+        Self::set_security_parameter(esp_ble_sm_param_t_ESP_BLE_SM_AUTHEN_REQ_MODE, &mut auth_req);
+        Self::set_security_parameter(
+            esp_ble_sm_param_t_ESP_BLE_SM_IOCAP_MODE,
+            &mut io_capabilities,
+        );
+        Self::set_security_parameter(
+            esp_ble_sm_param_t_ESP_BLE_SM_SET_INIT_KEY,
+            &mut initiator_keys,
+        );
+        Self::set_security_parameter(
+            esp_ble_sm_param_t_ESP_BLE_SM_SET_RSP_KEY,
+            &mut responder_keys,
+        );
+        Self::set_security_parameter(
+            esp_ble_sm_param_t_ESP_BLE_SM_MIN_KEY_SIZE,
+            &mut min_key_size,
+        );
+        Self::set_security_parameter(
+            esp_ble_sm_param_t_ESP_BLE_SM_MAX_KEY_SIZE,
+            &mut max_key_size,
+        );
+        Self::set_security_parameter(
+            esp_ble_sm_param_t_ESP_BLE_SM_ONLY_ACCEPT_SPECIFIED_SEC_AUTH,
+            &mut only_accept_specified,
+        );
+        Self::set_security_parameter(esp_ble_sm_param_t_ESP_BLE_SM_OOB_SUPPORT, &mut oob_support);
+
+        if let Some(mut passkey) = config.static_passkey {
+            Self::set_security_parameter(
+                esp_ble_sm_param_t_ESP_BLE_SM_SET_STATIC_PASSKEY,
+                &mut passkey,
+            );
+        } else {
+            let mut unused = 0u8;
+            Self::set_security_parameter(
+                esp_ble_sm_param_t_ESP_BLE_SM_CLEAR_STATIC_PASSKEY,
+                &mut unused,
+            );
+        }
+    }
+
+    /// Asks a newly connected peer to encrypt the link, if configured to do so.
+    pub(crate) fn request_security(&self, connection: &Connection) {
+        let Some(config) = self.security else {
+            return;
+        };
+
+        if !config.request_on_connect {
+            return;
+        }
+
+        debug!("Requesting security from {}.", connection);
+
+        let mut address = connection.remote_bda;
+        let result = unsafe {
+            esp!(esp_ble_set_encryption(
+                address.as_mut_ptr(),
+                config.encryption_action()
+            ))
+        };
+
+        if let Err(error) = result {
+            warn!("Cannot request security from {}: {}.", connection, error);
+        }
+    }
+
+    pub(crate) fn on_security_request(&self, param: esp_ble_sec_req_t) {
+        let mut address = param.bd_addr;
+        let accept = self.security.is_some();
+
+        if accept {
+            info!(
+                "Accepting security request from {}.",
+                BleAddress::public(address)
+            );
+        } else {
+            warn!(
+                "Rejecting security request from {}: security is not configured.",
+                BleAddress::public(address)
+            );
+        }
+
+        unsafe {
+            esp_nofail!(esp_ble_gap_security_rsp(address.as_mut_ptr(), accept));
+        }
+    }
+
+    pub(crate) fn on_authentication_complete(&mut self, param: esp_ble_auth_cmpl_t) {
+        let address = BleAddress::from_esp(param.bd_addr, param.addr_type);
+
+        let key_size = self.key_sizes.remove(&param.bd_addr).or_else(|| {
+            Self::bond_device_list()
+                .iter()
+                .find(|device| device.bd_addr == param.bd_addr)
+                .filter(|device| u32::from(device.bond_key.key_mask) & ESP_BLE_ENC_KEY_MASK != 0)
+                .map(|device| device.bond_key.penc_key.key_size)
+        });
+
+        let identity = param
+            .success
+            .then(|| Self::resolve_address(BleAddress::public(param.bd_addr)))
+            .flatten();
+
+        let security = if param.success {
+            info!(
+                "Authentication with {} complete (mode: {:#04X}, bonded: {}).",
+                address,
+                param.auth_mode,
+                identity.is_some()
+            );
+            LinkSecurity::from_auth_mode(param.auth_mode, key_size, identity.is_some())
+        } else {
+            warn!(
+                "Authentication with {} failed: {:#04X}.",
+                address, param.fail_reason
+            );
+            LinkSecurity::default()
+        };
+
+        let connection = Connection::connected()
+            .into_iter()
+            .find(|connection| connection.remote_bda == param.bd_addr);
+        if let Some(connection) = connection {
+            Connection::set_security(connection.id, security);
+        }
+
+        // The client subscriptions made before bonding are kept with the bond,
+        // and the first bonded peer becomes the administrator.
+        if let Some(identity) = identity {
+            // The Bluetooth stack might have dropped an older bond to make room for this one.
+            remove_orphaned_cccd_values();
+            persist_cccd_values(param.bd_addr, identity);
+            Self::claim_administrator(identity);
+
+            // The subscriptions stored with the bond apply once the peer is identified.
+            if let Some(connection) = connection {
+                self.report_subscriptions(connection);
+            }
+
+            self.indicate_service_changed(identity, param.bd_addr);
+        }
+
+        self.on_pairing_outcome(address, param);
+    }
+
+    pub(crate) fn on_key(&mut self, param: esp_ble_key_t) {
+        let key_type = match u32::from(param.key_type) {
+            ESP_LE_KEY_PENC => "peer encryption key",
+            ESP_LE_KEY_PID => "peer identity key",
+            ESP_LE_KEY_PCSRK => "peer signing key",
+            ESP_LE_KEY_PLK => "peer link key",
+            ESP_LE_KEY_LLK => "local link key",
+            ESP_LE_KEY_LENC => "local encryption key",
+            ESP_LE_KEY_LID => "local identity key",
+            ESP_LE_KEY_LCSRK => "local signing key",
+            _ => "unknown key",
+        };
+
+        debug!(
+            "Exchanged {} with {}.",
+            key_type,
+            BleAddress::public(param.bd_addr)
+        );
+
+        // Remember the key size until the authentication completes.
+        match u32::from(param.key_type) {
+            ESP_LE_KEY_PENC => {
+                let key_size = unsafe { param.p_key_value.penc_key.key_size };
+                self.key_sizes.insert(param.bd_addr, key_size);
+            }
+            ESP_LE_KEY_LENC => {
+                let key_size = unsafe { param.p_key_value.lenc_key.key_size };
+                self.key_sizes.insert(param.bd_addr, key_size);
+            }
+            _ => {}
+        }
+    }
+
+    fn set_security_parameter<T>(parameter: esp_ble_sm_param_t, value: &mut T) {
+        let result = unsafe {
+            esp!(esp_ble_gap_set_security_param(
+                parameter,
+                (value as *mut T).cast::<c_void>(),
+                std::mem::size_of::<T>() as u8
+            ))
+        };
+
+        if let Err(error) = result {
+            warn!("Cannot set security parameter {}: {}.", parameter, error);
+        }
+    }
+}
@@ -1,14 +1,20 @@
 use crate::{
-    gatt_server::characteristic::Characteristic, gatt_server::descriptor::Descriptor,
-    leaky_box_raw, utilities::BleUuid,
+    gatt_server::characteristic::Characteristic,
+    gatt_server::descriptor::Descriptor,
+    leaky_box_raw,
+    utilities::{AccessPolicy, AuthorizationCallback, AuthorizationRequest, BleUuid},
 };
 use esp_idf_sys::*;
-use log::debug;
+use log::{debug, warn};
 use std::{
     fmt::Formatter,
     sync::{Arc, RwLock},
+    time::{Duration, Instant},
 };
 
+/// How long the registration of a service waits for the services it includes to be registered.
+const INCLUDE_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Represents a GATT service.
 #[derive(Debug, Clone)]
 pub struct Service {
@@ -17,6 +23,11 @@ pub struct Service {
     pub(crate) characteristics: Vec<Arc<RwLock<Characteristic>>>,
     primary: bool,
     pub(crate) handle: Option<u16>,
+    pub(crate) authorization: Option<AuthorizationCallback>,
+    pub(crate) access_policy: Option<AccessPolicy>,
+    pub(crate) included_services: Vec<Arc<RwLock<Service>>>,
+    /// The handles of the include declarations registered so far, or `None` if the registration failed.
+    pub(crate) include_handles: Vec<Option<u16>>,
 }
 
 impl Service {
@@ -29,6 +40,10 @@ impl Service {
             characteristics: Vec::new(),
             primary: false,
             handle: None,
+            authorization: None,
+            access_policy: None,
+            included_services: Vec::new(),
+            include_handles: Vec::new(),
         }
     }
 
@@ -54,6 +69,48 @@ impl Service {
         self
     }
 
+    /// Includes another [`Service`] in this [`Service`].
+    ///
+    /// The include declaration is registered once the included service has its handle,
+    /// so the included service must be added to a [`Profile`] of the same server.
+    /// If it is not registered, or the include declaration is not confirmed, within 5 seconds,
+    /// the include declaration is skipped.
+    ///
+    /// [`Profile`]: crate::gatt_server::Profile
+    pub fn include(&mut self, service: &Arc<RwLock<Self>>) -> &mut Self {
+        self.included_services.push(service.clone());
+        self
+    }
+
+    /// Sets the [`AccessPolicy`] deciding which clients can read and write the characteristics of this [`Service`].
+    ///
+    /// A policy set on a [`Characteristic`] takes precedence.
+    pub fn access_policy(&mut self, policy: AccessPolicy) -> &mut Self {
+        self.access_policy = Some(policy);
+        self
+    }
+
+    /// Returns the [`AccessPolicy`] of this [`Service`], if any.
+    #[must_use]
+    pub const fn get_access_policy(&self) -> Option<&AccessPolicy> {
+        self.access_policy.as_ref()
+    }
+
+    /// Sets a callback that authorizes the access to this [`Service`].
+    ///
+    /// The callback receives the context of the access, and returns whether it is allowed.
+    /// Denied accesses are answered with an "Insufficient Authorization" error.
+    /// See [`GattServer::on_authorize`] for the details.
+    ///
+    /// [`GattServer::on_authorize`]: crate::gatt_server::GattServer::on_authorize
+    pub fn on_authorize<C: Fn(&AuthorizationRequest) -> bool + Send + Sync + 'static>(
+        &mut self,
+        callback: C,
+    ) -> &mut Self {
+        self.authorization = Some(AuthorizationCallback::new(callback));
+        self
+    }
+
     /// Returns a reference to the built [`Service`] behind an `Arc` and an `RwLock`.
     ///
     /// The returned value can be passed to any function of this crate that expects a [`Service`].
@@ -106,32 +163,112 @@ impl Service {
             is_primary: self.primary,
         };
 
+        // The service declaration, the include declarations, and the attributes of the characteristics.
+        let handle_count = 1
+            + self.included_services.len()
+            + self
+                .characteristics
+                .iter()
+                .map(|characteristic| characteristic.read().unwrap().handle_count())
+                .sum::<usize>();
+
         unsafe {
             esp_nofail!(esp_ble_gatts_create_service(
                 interface,
                 leaky_box_raw!(id),
-                256, // TODO: count the number of characteristics and descriptors.
+                handle_count as u16,
             ));
         }
     }
 
-    pub(crate) fn register_characteristics(&mut self) {
-        debug!("Registering {}'s characteristics.", &self);
+    /// Registers the include declarations and the characteristics of the [`Service`].
+    ///
+    /// The characteristics are guarded when the server, the service or the characteristic itself
+    /// authorizes the accesses or has an access policy, so that the Bluetooth stack leaves every access to the application.
+    pub(crate) fn register_characteristics(service: &Arc<RwLock<Self>>, guarded_by_server: bool) {
+        let this = service.read().unwrap();
+        debug!("Registering {}'s characteristics.", this);
 
         // Attention: The characteristics should be registered one after another.
         // We need to wait for the previous characteristic to be registered before we can register the next one.
+        // The include declarations come first, as required by the specification.
 
-        if self.characteristics.is_empty() {
+        if this.characteristics.is_empty() && this.included_services.is_empty() {
             return;
         }
 
         // Loghi docet.
 
-        let service_handle = self.handle.unwrap();
-        let characteristics = self.characteristics.clone();
+        let service = service.clone();
+        let service_handle = this.handle.unwrap();
+        let included_services = this.included_services.clone();
+        let characteristics = this.characteristics.clone();
+        let guarded =
+            guarded_by_server || this.authorization.is_some() || this.access_policy.is_some();
         std::thread::spawn(move || {
+            for included_service in &included_services {
+                let deadline = Instant::now() + INCLUDE_TIMEOUT;
+                let included_handle = loop {
+                    if let Some(handle) = included_service.read().unwrap().handle {
+                        break Some(handle);
+                    }
+                    if Instant::now() >= deadline {
+                        break None;
+                    }
+                    std::thread::yield_now();
+                };
+
+                let Some(included_handle) = included_handle else {
+                    warn!(
+                        "{} is not registered after {:?}. Not including it in {}.",
+                        included_service.read().unwrap(),
+                        INCLUDE_TIMEOUT,
+                        service.read().unwrap()
+                    );
+                    continue;
+                };
+
+                let expected = service.read().unwrap().include_handles.len() + 1;
+                let result = unsafe {
+                    esp!(esp_ble_gatts_add_included_service(
+                        service_handle,
+                        included_handle
+                    ))
+                };
+
+                if let Err(error) = result {
+                    warn!(
+                        "Cannot include {} in {}: {}.",
+                        included_service.read().unwrap(),
+                        service.read().unwrap(),
+                        error
+                    );
+                    continue;
+                }
+
+                let deadline = Instant::now() + INCLUDE_TIMEOUT;
+                while service.read().unwrap().include_handles.len() < expected {
+                    if Instant::now() >= deadline {
+                        warn!(
+                            "The inclusion of {} in {} was not confirmed after {:?}. Skipping it.",
+                            included_service.read().unwrap(),
+                            service.read().unwrap(),
+                            INCLUDE_TIMEOUT
+                        );
+                        break;
+                    }
+                    std::thread::yield_now();
+                }
+            }
+
             for c in characteristics {
-                c.write().unwrap().register_self(service_handle);
+                let mut characteristic = c.write().unwrap();
+                characteristic.guarded = guarded
+                    || characteristic.authorization.is_some()
+                    || characteristic.access_policy.is_some();
+                characteristic.register_self(service_handle);
+                drop(characteristic);
+
                 while c.read().unwrap().attribute_handle.is_none() {
                     std::thread::yield_now();
                 }
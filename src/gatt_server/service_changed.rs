@@ -0,0 +1,141 @@
+use esp_idf_sys::*;
+use log::{info, warn};
+
+use crate::{
+    gatt_server::{persistence::fnv1a, storage::with_storage, GattServer},
+    utilities::BleAddress,
+};
+
+/// The storage key of the fingerprint of the attribute table.
+const ATTRIBUTE_TABLE_KEY: &str = "gatt_hash";
+
+/// The storage key of the identity addresses of the bonded peers that must be told the attribute table changed.
+const SERVICE_CHANGED_KEY: &str = "gatt_changed";
+
+impl GattServer {
+    /// Compares the attribute table with the one of the previous start.
+    ///
+    /// If it changed, the bonded peers are told with a Service Changed indication once they reconnect,
+    /// so that they discover the attributes again instead of using the handles they cached.
+    pub(crate) fn check_attribute_table(&self) {
+        let fingerprint = self.attribute_table_fingerprint().to_le_bytes();
+
+        let result = with_storage(|storage| -> Result<bool, EspError> {
+            let stored = storage.get(ATTRIBUTE_TABLE_KEY)?;
+            if stored.as_deref() == Some(&fingerprint[..]) {
+                return Ok(false);
+            }
+
+            // The peers that did not reconnect since a previous change are still listed.
+            if stored.is_some() {
+                let pending: Vec<u8> = Self::bonded_devices()
+                    .iter()
+                    .flat_map(BleAddress::bytes)
+                    .collect();
+                storage.set(SERVICE_CHANGED_KEY, &pending)?;
+            }
+
+            storage.set(ATTRIBUTE_TABLE_KEY, &fingerprint)?;
+            Ok(stored.is_some())
+        });
+
+        match result {
+            Ok(true) => {
+                info!("The attribute table changed. Bonded peers will be told on reconnection.")
+            }
+            Ok(false) => {}
+            Err(error) => warn!("Cannot check the attribute table: {}.", error),
+        }
+    }
+
+    /// Sends a Service Changed indication to a bonded peer, if the attribute table changed since it last connected.
+    pub(crate) fn indicate_service_changed(&self, identity: BleAddress, mut address: [u8; 6]) {
+        let pending = match with_storage(|storage| storage.get(SERVICE_CHANGED_KEY)) {
+            Ok(pending) => pending.unwrap_or_default(),
+            Err(error) => {
+                warn!(
+                    "Cannot read the peers to tell about the attribute table: {}.",
+                    error
+                );
+                return;
+            }
+        };
+
+        if !pending.chunks(6).any(|peer| peer == identity.bytes()) {
+            return;
+        }
+
+        let Some(gatts_if) = self
+            .profiles
+            .iter()
+            .find_map(|profile| profile.read().unwrap().interface)
+        else {
+            warn!(
+                "No registered profile to tell {} about the attribute table.",
+                identity
+            );
+            return;
+        };
+
+        info!("Telling {} that the attribute table changed.", identity);
+        let result = unsafe {
+            esp!(esp_ble_gatts_send_service_change_indication(
+                gatts_if,
+                address.as_mut_ptr()
+            ))
+        };
+
+        if let Err(error) = result {
+            warn!(
+                "Cannot send Service Changed indication to {}: {}.",
+                identity, error
+            );
+            return;
+        }
+
+        let remaining: Vec<u8> = pending
+            .chunks(6)
+            .filter(|peer| *peer != identity.bytes())
+            .flatten()
+            .copied()
+            .collect();
+
+        if let Err(error) = with_storage(|storage| storage.set(SERVICE_CHANGED_KEY, &remaining)) {
+            warn!(
+                "Cannot store the peers to tell about the attribute table: {}.",
+                error
+            );
+        }
+    }
+
+    /// Returns a fingerprint of the attributes of the registered profiles and of their layout.
+    fn attribute_table_fingerprint(&self) -> u64 {
+        let mut layout = String::new();
+
+        for profile in &self.profiles {
+            for service in &profile.read().unwrap().services {
+                let service = service.read().unwrap();
+                layout.push_str(&format!("s{};", service.uuid));
+
+                for included in &service.included_services {
+                    layout.push_str(&format!("i{};", included.read().unwrap().uuid));
+                }
+
+                for characteristic in &service.characteristics {
+                    let characteristic = characteristic.read().unwrap();
+                    layout.push_str(&format!(
+                        "c{}:{};",
+                        characteristic.uuid,
+                        esp_gatt_char_prop_t::from(characteristic.properties)
+                    ));
+
+                    for descriptor in &characteristic.descriptors {
+                        layout.push_str(&format!("d{};", descriptor.read().unwrap().uuid));
+                    }
+                }
+            }
+        }
+
+        fnv1a(layout.as_bytes())
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use log::warn;
+
+use crate::{
+    gatt_server::GattServer,
+    utilities::{MemoryStorage, Storage},
+};
+
+lazy_static! {
+    /// The storage of the persisted state, in memory until the application sets one.
+    ///
+    /// It is kept apart from the server, so that the read and write callbacks can use it.
+    static ref STORAGE: Mutex<Box<dyn Storage>> = Mutex::new(Box::new(MemoryStorage::new()));
+}
+
+impl GattServer {
+    /// Sets the [`Storage`] of the state that the crate persists, such as the CCCDs of bonded clients.
+    ///
+    /// This must be set before starting the server. Without a storage, the server keeps its state
+    /// in a [`MemoryStorage`], and warns about it when it starts: the subscriptions of the bonded clients
+    /// and the persistent values are then lost on reboot.
+    ///
+    /// Pass an [`NvsStorage`] to keep the state in a namespace of an NVS partition taken by the application.
+    ///
+    /// [`NvsStorage`]: crate::utilities::NvsStorage
+    pub fn storage<S: Storage + 'static>(&mut self, storage: S) -> &mut Self {
+        *STORAGE.lock().unwrap() = Box::new(storage);
+        self.storage_set = true;
+        self
+    }
+
+    /// Warns that the persisted state is kept in memory if the application did not set a storage.
+    pub(crate) fn check_storage(&self) {
+        if !self.storage_set {
+            warn!("No storage set with GattServer::storage. The persisted state will be lost on reboot.");
+        }
+    }
+}
+
+/// Runs `f` with the storage of the persisted state.
+pub(crate) fn with_storage<R>(f: impl FnOnce(&mut dyn Storage) -> R) -> R {
+    f(STORAGE.lock().unwrap().as_mut())
+}
@@ -20,3 +20,6 @@ pub mod gatt_server;
 
 #[cfg(not(esp32s2))]
 pub mod utilities;
+
+#[cfg(not(esp32s2))]
+pub mod bthome;
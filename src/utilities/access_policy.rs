@@ -0,0 +1,172 @@
+use esp_idf_sys::*;
+
+use crate::{
+    gatt_server::GattServer,
+    utilities::{AttributeOperation, AuthorizationRequest, BleAddress},
+};
+
+/// A condition on the client accessing an attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessRule {
+    /// Any client.
+    Anyone,
+    /// No client.
+    Nobody,
+    /// Clients on an encrypted link.
+    Encrypted,
+    /// Bonded clients, once the link is encrypted with the key of the bond.
+    Bonded,
+    /// Clients whose identity address is in the list.
+    Addresses(Vec<BleAddress>),
+    /// The client that bonded first, typically the owner of the device.
+    ///
+    /// See [`GattServer::administrator`].
+    ///
+    /// [`GattServer::administrator`]: crate::gatt_server::GattServer::administrator
+    FirstBonded,
+    /// Clients that satisfy all the rules.
+    All(Vec<AccessRule>),
+    /// Clients that satisfy at least one of the rules.
+    Any(Vec<AccessRule>),
+}
+
+impl AccessRule {
+    /// Checks whether the rule allows an access.
+    ///
+    /// Returns the ATT error to answer with if it does not.
+    pub(crate) fn check(&self, request: &AuthorizationRequest) -> Result<(), esp_gatt_status_t> {
+        match self {
+            Self::Anyone => Ok(()),
+            Self::Nobody => Err(not_permitted(request.operation)),
+            Self::Encrypted => {
+                if request.security.encrypted {
+                    Ok(())
+                } else {
+                    Err(esp_gatt_status_t_ESP_GATT_INSUF_ENCRYPTION)
+                }
+            }
+            Self::Bonded => {
+                if request.security.bonded {
+                    Ok(())
+                } else {
+                    Err(esp_gatt_status_t_ESP_GATT_INSUF_AUTHENTICATION)
+                }
+            }
+            Self::Addresses(addresses) => {
+                let identity = request
+                    .connection
+                    .identity_address()
+                    .unwrap_or_else(|| BleAddress::public(request.connection.remote_address()));
+
+                if addresses.contains(&identity) {
+                    Ok(())
+                } else {
+                    Err(esp_gatt_status_t_ESP_GATT_INSUF_AUTHORIZATION)
+                }
+            }
+            Self::FirstBonded => {
+                if !request.security.bonded {
+                    return Err(esp_gatt_status_t_ESP_GATT_INSUF_AUTHENTICATION);
+                }
+
+                let administrator = GattServer::administrator();
+                if administrator.is_some() && administrator == request.connection.identity_address()
+                {
+                    Ok(())
+                } else {
+                    Err(esp_gatt_status_t_ESP_GATT_INSUF_AUTHORIZATION)
+                }
+            }
+            Self::All(rules) => rules.iter().try_for_each(|rule| rule.check(request)),
+            Self::Any(rules) => {
+                let mut error = not_permitted(request.operation);
+
+                for rule in rules {
+                    match rule.check(request) {
+                        Ok(()) => return Ok(()),
+                        Err(status) => error = status,
+                    }
+                }
+
+                Err(error)
+            }
+        }
+    }
+}
+
+/// A set of rules deciding which clients can read and write an attribute.
+///
+/// Policies are checked on top of the [`AttributePermissions`], before the read and write callbacks run.
+/// A denied access is answered with the ATT error matching the failed rule.
+///
+/// Attributes with a policy are answered by the application instead of the Bluetooth stack,
+/// so that every read and write is checked, including long writes. Policies set on a [`Service`]
+/// or a [`Characteristic`] also apply to its descriptors, such as the CCCD.
+///
+/// [`AttributePermissions`]: crate::utilities::AttributePermissions
+/// [`Service`]: crate::gatt_server::Service
+/// [`Characteristic`]: crate::gatt_server::Characteristic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessPolicy {
+    read: AccessRule,
+    write: AccessRule,
+}
+
+impl Default for AccessPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AccessPolicy {
+    /// Creates a new [`AccessPolicy`] that allows any client.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            read: AccessRule::Anyone,
+            write: AccessRule::Anyone,
+        }
+    }
+
+    /// Sets the rule for reads.
+    #[must_use]
+    pub fn read(mut self, rule: AccessRule) -> Self {
+        self.read = rule;
+        self
+    }
+
+    /// Sets the rule for writes.
+    #[must_use]
+    pub fn write(mut self, rule: AccessRule) -> Self {
+        self.write = rule;
+        self
+    }
+
+    /// Returns the rule for reads.
+    #[must_use]
+    pub const fn read_rule(&self) -> &AccessRule {
+        &self.read
+    }
+
+    /// Returns the rule for writes.
+    #[must_use]
+    pub const fn write_rule(&self) -> &AccessRule {
+        &self.write
+    }
+
+    /// Checks whether the policy allows an access.
+    pub(crate) fn check(&self, request: &AuthorizationRequest) -> Result<(), esp_gatt_status_t> {
+        match request.operation {
+            AttributeOperation::Read => self.read.check(request),
+            AttributeOperation::Write => self.write.check(request),
+        }
+    }
+}
+
+/// Returns the ATT error for an operation that is not permitted at all.
+const fn not_permitted(operation: AttributeOperation) -> esp_gatt_status_t {
+    match operation {
+        AttributeOperation::Read => esp_gatt_status_t_ESP_GATT_READ_NOT_PERMIT,
+        AttributeOperation::Write => esp_gatt_status_t_ESP_GATT_WRITE_NOT_PERMIT,
+    }
+}
@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+/// The address that the device uses over the air.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressMode {
+    /// The public address of the device.
+    #[default]
+    Public,
+    /// A static random address, that stays the same until the next power cycle or until changed.
+    ///
+    /// The two most significant bits of the first byte must be set, as required by the specification.
+    StaticRandom([u8; 6]),
+    /// A resolvable private address, regenerated after every rotation timeout.
+    ///
+    /// Bonded peers can resolve it with the identity resolving key distributed during pairing,
+    /// while other peers cannot link it to the device's identity.
+    ResolvablePrivate,
+}
+
+/// The default rotation timeout for resolvable private addresses, as recommended by the specification.
+pub(crate) const DEFAULT_RPA_TIMEOUT: Duration = Duration::from_secs(15 * 60);
@@ -1,40 +1,187 @@
 use esp_idf_sys::*;
 
+/// The security level required to read an attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadLevel {
+    /// Any client can read the attribute.
+    #[default]
+    Open,
+    /// The link must be encrypted.
+    Encrypted,
+    /// The link must be encrypted with a key from an authenticated (MITM-protected) pairing.
+    EncryptedMitm,
+}
+
+/// The security level required to write an attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WriteLevel {
+    /// Any client can write the attribute.
+    #[default]
+    Open,
+    /// The link must be encrypted.
+    Encrypted,
+    /// The link must be encrypted with a key from an authenticated (MITM-protected) pairing.
+    EncryptedMitm,
+    /// Writes must be signed with the signing key of a bonded client.
+    ///
+    /// The characteristic should also have the "authenticated signed writes" property.
+    Signed,
+    /// Writes must be signed with the signing key from an authenticated (MITM-protected) pairing.
+    SignedMitm,
+}
+
 /// Represents an attribute's access permissions.
 ///
 /// This struct is used to set the permissions of a [`Characteristic`] or a [`Descriptor`].
-/// It can represent read and write permissions, and encryption requirements.
+/// Read and write access have separate security levels, and can each require authorization
+/// by the application.
+///
+/// The constructor and the builder methods are `const`, so permissions built in a `const` item,
+/// such as `const PERMISSIONS: AttributePermissions = AttributePermissions::new().read().encrypted();`,
+/// are checked at compile time. Elsewhere, invalid combinations panic when they are built.
+///
+/// [`Characteristic`]: crate::gatt_server::Characteristic
+/// [`Descriptor`]: crate::gatt_server::Descriptor
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Clone, Copy, Default)]
 pub struct AttributePermissions {
-    pub(crate) read_access: bool,
-    pub(crate) write_access: bool,
-    pub(crate) encryption_required: bool,
+    pub(crate) read_access: Option<ReadLevel>,
+    pub(crate) write_access: Option<WriteLevel>,
+    pub(crate) read_authorization: bool,
+    pub(crate) write_authorization: bool,
+    encryption_required: bool,
 }
 
 impl AttributePermissions {
-    /// Creates a new [`AttributePermissions`].
+    /// Creates a new [`AttributePermissions`], without read or write access.
     #[must_use]
-    pub fn new() -> Self {
-        Self::default()
+    pub const fn new() -> Self {
+        Self {
+            read_access: None,
+            write_access: None,
+            read_authorization: false,
+            write_authorization: false,
+            encryption_required: false,
+        }
     }
 
     /// Sets the read access of the [`AttributePermissions`].
+    ///
+    /// The read level is [`ReadLevel::Open`], or [`ReadLevel::Encrypted`] if encryption is required.
     #[must_use]
-    pub const fn read(mut self) -> Self {
-        self.read_access = true;
-        self
+    pub const fn read(self) -> Self {
+        if self.encryption_required {
+            self.read_with(ReadLevel::Encrypted)
+        } else {
+            self.read_with(ReadLevel::Open)
+        }
     }
 
     /// Sets the write access of the [`AttributePermissions`].
+    ///
+    /// The write level is [`WriteLevel::Open`], or [`WriteLevel::Encrypted`] if encryption is required.
+    #[must_use]
+    pub const fn write(self) -> Self {
+        if self.encryption_required {
+            self.write_with(WriteLevel::Encrypted)
+        } else {
+            self.write_with(WriteLevel::Open)
+        }
+    }
+
+    /// Sets the read access of the [`AttributePermissions`], with the given security level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if encryption is required and the level is [`ReadLevel::Open`].
+    #[must_use]
+    pub const fn read_with(mut self, level: ReadLevel) -> Self {
+        assert!(
+            !(self.encryption_required && matches!(level, ReadLevel::Open)),
+            "Cannot allow unencrypted reads when encryption is required."
+        );
+
+        self.read_access = Some(level);
+        self
+    }
+
+    /// Sets the write access of the [`AttributePermissions`], with the given security level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if encryption is required and the level is [`WriteLevel::Open`] or a signed level,
+    /// since signed writes are meant for unencrypted links.
     #[must_use]
-    pub const fn write(mut self) -> Self {
-        self.write_access = true;
+    pub const fn write_with(mut self, level: WriteLevel) -> Self {
+        assert!(
+            !(self.encryption_required
+                && matches!(
+                    level,
+                    WriteLevel::Open | WriteLevel::Signed | WriteLevel::SignedMitm
+                )),
+            "Cannot allow unencrypted or signed writes when encryption is required."
+        );
+
+        self.write_access = Some(level);
+        self
+    }
+
+    /// Requires the application to authorize every read.
+    ///
+    /// # Panics
+    ///
+    /// Panics if read access is not set.
+    #[must_use]
+    pub const fn read_authorized(mut self) -> Self {
+        assert!(
+            self.read_access.is_some(),
+            "Cannot require read authorization without read access."
+        );
+
+        self.read_authorization = true;
+        self
+    }
+
+    /// Requires the application to authorize every write.
+    ///
+    /// # Panics
+    ///
+    /// Panics if write access is not set.
+    #[must_use]
+    pub const fn write_authorized(mut self) -> Self {
+        assert!(
+            self.write_access.is_some(),
+            "Cannot require write authorization without write access."
+        );
+
+        self.write_authorization = true;
         self
     }
 
     /// Sets the encryption requirement of the [`AttributePermissions`].
+    ///
+    /// Open read and write access is raised to the encrypted level, including access set afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if signed writes are allowed.
     #[must_use]
     pub const fn encrypted(mut self) -> Self {
+        assert!(
+            !matches!(
+                self.write_access,
+                Some(WriteLevel::Signed | WriteLevel::SignedMitm)
+            ),
+            "Cannot require encryption for signed writes."
+        );
+
+        if matches!(self.read_access, Some(ReadLevel::Open)) {
+            self.read_access = Some(ReadLevel::Encrypted);
+        }
+        if matches!(self.write_access, Some(WriteLevel::Open)) {
+            self.write_access = Some(WriteLevel::Encrypted);
+        }
+
         self.encryption_required = true;
         self
     }
@@ -43,21 +190,29 @@ impl AttributePermissions {
 impl From<AttributePermissions> for esp_gatt_perm_t {
     #[allow(clippy::cast_possible_truncation)]
     fn from(permissions: AttributePermissions) -> Self {
-        let result = match (
-            permissions.read_access,
-            permissions.write_access,
-            permissions.encryption_required,
-        ) {
-            // TODO: Implement all the supported modes.
-            (false, false, _) => 0,
-            (true, false, false) => ESP_GATT_PERM_READ,
-            (false, true, false) => ESP_GATT_PERM_WRITE,
-            (true, true, false) => ESP_GATT_PERM_READ | ESP_GATT_PERM_WRITE,
-            (true, false, true) => ESP_GATT_PERM_READ_ENCRYPTED,
-            (false, true, true) => ESP_GATT_PERM_WRITE_ENCRYPTED,
-            (true, true, true) => ESP_GATT_PERM_READ_ENCRYPTED | ESP_GATT_PERM_WRITE_ENCRYPTED,
+        let mut result = match permissions.read_access {
+            None => 0,
+            Some(ReadLevel::Open) => ESP_GATT_PERM_READ,
+            Some(ReadLevel::Encrypted) => ESP_GATT_PERM_READ_ENCRYPTED,
+            Some(ReadLevel::EncryptedMitm) => ESP_GATT_PERM_READ_ENC_MITM,
         };
 
+        result |= match permissions.write_access {
+            None => 0,
+            Some(WriteLevel::Open) => ESP_GATT_PERM_WRITE,
+            Some(WriteLevel::Encrypted) => ESP_GATT_PERM_WRITE_ENCRYPTED,
+            Some(WriteLevel::EncryptedMitm) => ESP_GATT_PERM_WRITE_ENC_MITM,
+            Some(WriteLevel::Signed) => ESP_GATT_PERM_WRITE_SIGNED,
+            Some(WriteLevel::SignedMitm) => ESP_GATT_PERM_WRITE_SIGNED_MITM,
+        };
+
+        if permissions.read_authorization {
+            result |= ESP_GATT_PERM_READ_AUTHORIZATION;
+        }
+        if permissions.write_authorization {
+            result |= ESP_GATT_PERM_WRITE_AUTHORIZATION;
+        }
+
         result as Self
     }
 }
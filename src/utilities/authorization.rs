@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use crate::utilities::{BleUuid, Connection, LinkSecurity};
+
+/// The kind of access a client requests on an attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeOperation {
+    /// The client reads the attribute.
+    Read,
+    /// The client writes the attribute.
+    Write,
+}
+
+/// The context of an attribute access, passed to the authorization callbacks.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthorizationRequest {
+    /// The connection of the requesting client.
+    pub connection: Connection,
+    /// The security state of the link, including whether it is encrypted and whether the client is bonded.
+    pub security: LinkSecurity,
+    /// The UUID of the accessed attribute.
+    pub attribute: BleUuid,
+    /// The handle of the accessed attribute.
+    pub handle: u16,
+    /// The requested operation.
+    pub operation: AttributeOperation,
+}
+
+/// A callback that allows or denies an attribute access.
+#[derive(Clone)]
+pub(crate) struct AuthorizationCallback(
+    pub(crate) Arc<dyn Fn(&AuthorizationRequest) -> bool + Send + Sync>,
+);
+
+impl AuthorizationCallback {
+    pub(crate) fn new<C: Fn(&AuthorizationRequest) -> bool + Send + Sync + 'static>(
+        callback: C,
+    ) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    pub(crate) fn allows(&self, request: &AuthorizationRequest) -> bool {
+        (self.0)(request)
+    }
+}
+
+impl std::fmt::Debug for AuthorizationCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "authorization callback")
+    }
+}
@@ -0,0 +1,104 @@
+use esp_idf_sys::{
+    esp_ble_addr_type_t, esp_ble_addr_type_t_BLE_ADDR_TYPE_PUBLIC,
+    esp_ble_addr_type_t_BLE_ADDR_TYPE_RANDOM, esp_ble_addr_type_t_BLE_ADDR_TYPE_RPA_PUBLIC,
+};
+
+/// The type of a [`BleAddress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BleAddressType {
+    /// A public device address, assigned by the manufacturer.
+    #[default]
+    Public,
+    /// A random device address: static, resolvable private or non-resolvable private.
+    Random,
+}
+
+/// A Bluetooth device address.
+///
+/// The address bytes are stored in the usual human-readable order, most significant byte first.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BleAddress {
+    address: [u8; 6],
+    address_type: BleAddressType,
+}
+
+impl BleAddress {
+    /// Creates a new public [`BleAddress`].
+    #[must_use]
+    pub const fn public(address: [u8; 6]) -> Self {
+        Self {
+            address,
+            address_type: BleAddressType::Public,
+        }
+    }
+
+    /// Creates a new random [`BleAddress`].
+    #[must_use]
+    pub const fn random(address: [u8; 6]) -> Self {
+        Self {
+            address,
+            address_type: BleAddressType::Random,
+        }
+    }
+
+    /// Returns the address bytes, most significant byte first.
+    #[must_use]
+    pub const fn bytes(&self) -> [u8; 6] {
+        self.address
+    }
+
+    /// Returns the type of the [`BleAddress`].
+    #[must_use]
+    pub const fn address_type(&self) -> BleAddressType {
+        self.address_type
+    }
+
+    /// Returns `true` if this is a static random address.
+    #[must_use]
+    pub const fn is_static_random(&self) -> bool {
+        matches!(self.address_type, BleAddressType::Random) && self.address[0] >> 6 == 0b11
+    }
+
+    /// Returns `true` if this is a resolvable private address.
+    #[must_use]
+    pub const fn is_resolvable_private(&self) -> bool {
+        matches!(self.address_type, BleAddressType::Random) && self.address[0] >> 6 == 0b01
+    }
+
+    pub(crate) fn from_esp(address: [u8; 6], address_type: esp_ble_addr_type_t) -> Self {
+        #[allow(non_upper_case_globals)]
+        match address_type {
+            esp_ble_addr_type_t_BLE_ADDR_TYPE_PUBLIC
+            | esp_ble_addr_type_t_BLE_ADDR_TYPE_RPA_PUBLIC => Self::public(address),
+            _ => Self::random(address),
+        }
+    }
+
+    pub(crate) const fn esp_address_type(&self) -> esp_ble_addr_type_t {
+        match self.address_type {
+            BleAddressType::Public => esp_ble_addr_type_t_BLE_ADDR_TYPE_PUBLIC,
+            BleAddressType::Random => esp_ble_addr_type_t_BLE_ADDR_TYPE_RANDOM,
+        }
+    }
+}
+
+impl std::fmt::Display for BleAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}",
+            self.address[0],
+            self.address[1],
+            self.address[2],
+            self.address[3],
+            self.address[4],
+            self.address[5],
+        )
+    }
+}
+
+impl std::fmt::Debug for BleAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self} ({:?})", self.address_type)
+    }
+}
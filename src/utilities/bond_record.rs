@@ -0,0 +1,352 @@
+use esp_idf_sys::*;
+
+use crate::utilities::BleAddress;
+
+/// Identifies a bond blob.
+const BLOB_MAGIC: [u8; 4] = *b"BOND";
+
+/// The current version of the bond blob format.
+const BLOB_VERSION: u8 = 1;
+
+/// The size of the blob header: magic, version and record count.
+const HEADER_SIZE: usize = 6;
+
+/// The size of an encoded record.
+const RECORD_SIZE: usize = 68;
+
+// Bit flags of an encoded record.
+const FLAG_AUTHENTICATED: u8 = 1 << 0;
+const FLAG_IDENTITY: u8 = 1 << 1;
+const FLAG_SIGNING: u8 = 1 << 2;
+const FLAGS: u8 = FLAG_AUTHENTICATED | FLAG_IDENTITY | FLAG_SIGNING;
+
+/// The valid sizes of an encryption key, in bytes.
+const KEY_SIZES: std::ops::RangeInclusive<u8> = 7..=16;
+
+// Security levels, as stored by the Bluetooth stack.
+const SEC_LEVEL_UNAUTHENTICATED: u8 = 1 << 0;
+const SEC_LEVEL_AUTHENTICATED: u8 = 1 << 2;
+
+/// The keys of a bonded peer, as exported by [`GattServer::export_bonds`].
+///
+/// Only LE Secure Connections bonds can be exported: with legacy pairing,
+/// the key the peer uses to re-encrypt the link is not exposed by the Bluetooth stack.
+///
+/// [`GattServer::export_bonds`]: crate::gatt_server::GattServer::export_bonds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BondRecord {
+    /// The address the peer bonded with.
+    pub address: BleAddress,
+    /// The identity address and identity resolving key of the peer, if it distributed them.
+    pub identity: Option<(BleAddress, [u8; 16])>,
+    /// The long term key.
+    pub ltk: [u8; 16],
+    /// The size of the long term key in bytes.
+    pub key_size: u8,
+    /// Whether the bond comes from an authenticated (MITM-protected) pairing.
+    pub authenticated: bool,
+    /// The connection signature resolving key of the peer and its sign counter, if it distributed them.
+    pub csrk: Option<([u8; 16], u32)>,
+}
+
+/// The bonds exported by [`GattServer::export_bonds`].
+///
+/// [`GattServer::export_bonds`]: crate::gatt_server::GattServer::export_bonds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BondExport {
+    /// The blob of the exported bonds, see [`BondRecord::to_blob`].
+    pub blob: Vec<u8>,
+    /// The peers whose legacy bonds cannot be exported. They have to pair again with a replacement device.
+    pub skipped: Vec<BleAddress>,
+}
+
+/// The reasons a bond blob can be rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BondBlobError {
+    /// The blob does not start with the expected header.
+    InvalidHeader,
+    /// The blob was written by an unsupported version of the format.
+    UnsupportedVersion(u8),
+    /// The blob is shorter or longer than its record count implies.
+    InvalidLength,
+    /// A record has unknown flags or an invalid key size.
+    InvalidRecord,
+    /// There are more than 255 records to write in a blob.
+    TooManyRecords,
+    /// The GATT server has not started, so the Bluetooth stack cannot store bonds.
+    NotStarted,
+    /// The Bluetooth stack refused to store a bond.
+    StorageFailed(BleAddress),
+}
+
+impl std::fmt::Display for BondBlobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidHeader => write!(f, "invalid bond blob header"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported bond blob version {version}")
+            }
+            Self::InvalidLength => write!(f, "invalid bond blob length"),
+            Self::InvalidRecord => write!(f, "invalid bond record"),
+            Self::TooManyRecords => write!(f, "too many bond records"),
+            Self::NotStarted => write!(f, "the GATT server has not started"),
+            Self::StorageFailed(address) => write!(f, "cannot store the bond with {address}"),
+        }
+    }
+}
+
+impl std::error::Error for BondBlobError {}
+
+impl BondRecord {
+    /// Creates a record from a bonded device reported by the Bluetooth stack, and the address it bonded with.
+    ///
+    /// Returns `None` for bonds without a long term key, or with a legacy pairing key.
+    pub(crate) fn from_bond_device(
+        device: &esp_ble_bond_dev_t,
+        address: BleAddress,
+    ) -> Option<Self> {
+        let key_mask = u32::from(device.bond_key.key_mask);
+        let penc_key = device.bond_key.penc_key;
+
+        // Secure Connections keys are stored with an empty diversifier.
+        if key_mask & ESP_BLE_ENC_KEY_MASK == 0 || penc_key.ediv != 0 || penc_key.rand != [0; 8] {
+            return None;
+        }
+
+        let pid_key = device.bond_key.pid_key;
+        let pcsrk_key = device.bond_key.pcsrk_key;
+
+        Some(Self {
+            address,
+            identity: (key_mask & ESP_BLE_ID_KEY_MASK != 0).then(|| {
+                (
+                    BleAddress::from_esp(pid_key.static_addr, pid_key.addr_type),
+                    pid_key.irk,
+                )
+            }),
+            ltk: penc_key.ltk,
+            key_size: penc_key.key_size,
+            authenticated: penc_key.sec_level & SEC_LEVEL_AUTHENTICATED != 0,
+            csrk: (key_mask & ESP_BLE_CSR_KEY_MASK != 0)
+                .then_some((pcsrk_key.csrk, pcsrk_key.counter)),
+        })
+    }
+
+    /// Serialises records into a versioned binary blob.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are more than 255 records.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn to_blob(records: &[Self]) -> Result<Vec<u8>, BondBlobError> {
+        let count = u8::try_from(records.len()).map_err(|_| BondBlobError::TooManyRecords)?;
+
+        let mut blob = Vec::with_capacity(HEADER_SIZE + records.len() * RECORD_SIZE);
+        blob.extend_from_slice(&BLOB_MAGIC);
+        blob.push(BLOB_VERSION);
+        blob.push(count);
+
+        for record in records {
+            let mut flags = 0;
+            if record.authenticated {
+                flags |= FLAG_AUTHENTICATED;
+            }
+            if record.identity.is_some() {
+                flags |= FLAG_IDENTITY;
+            }
+            if record.csrk.is_some() {
+                flags |= FLAG_SIGNING;
+            }
+
+            let (identity, irk) = record
+                .identity
+                .unwrap_or((BleAddress::public([0; 6]), [0; 16]));
+            let (csrk, counter) = record.csrk.unwrap_or(([0; 16], 0));
+
+            blob.push(flags);
+            blob.extend_from_slice(&record.address.bytes());
+            blob.push(record.address.esp_address_type() as u8);
+            blob.extend_from_slice(&record.ltk);
+            blob.push(record.key_size);
+            blob.extend_from_slice(&identity.bytes());
+            blob.push(identity.esp_address_type() as u8);
+            blob.extend_from_slice(&irk);
+            blob.extend_from_slice(&csrk);
+            blob.extend_from_slice(&counter.to_le_bytes());
+        }
+
+        Ok(blob)
+    }
+
+    /// Parses the records of a blob written by [`BondRecord::to_blob`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the blob is malformed or written by an unsupported version of the format,
+    /// or if a record has unknown flags or an invalid key size.
+    pub fn from_blob(blob: &[u8]) -> Result<Vec<Self>, BondBlobError> {
+        if blob.len() < HEADER_SIZE || blob[0..4] != BLOB_MAGIC {
+            return Err(BondBlobError::InvalidHeader);
+        }
+
+        if blob[4] != BLOB_VERSION {
+            return Err(BondBlobError::UnsupportedVersion(blob[4]));
+        }
+
+        let records = &blob[HEADER_SIZE..];
+        if records.len() != usize::from(blob[5]) * RECORD_SIZE {
+            return Err(BondBlobError::InvalidLength);
+        }
+
+        records
+            .chunks_exact(RECORD_SIZE)
+            .map(|record| {
+                let flags = record[0];
+                if flags & !FLAGS != 0 || !KEY_SIZES.contains(&record[24]) {
+                    return Err(BondBlobError::InvalidRecord);
+                }
+
+                let bytes = |range: std::ops::Range<usize>| -> [u8; 16] {
+                    record[range].try_into().unwrap()
+                };
+                let address = |offset: usize| {
+                    BleAddress::from_esp(
+                        record[offset..offset + 6].try_into().unwrap(),
+                        esp_ble_addr_type_t::from(record[offset + 6]),
+                    )
+                };
+
+                Ok(Self {
+                    address: address(1),
+                    ltk: bytes(8..24),
+                    key_size: record[24],
+                    identity: (flags & FLAG_IDENTITY != 0).then(|| (address(25), bytes(32..48))),
+                    authenticated: flags & FLAG_AUTHENTICATED != 0,
+                    csrk: (flags & FLAG_SIGNING != 0).then(|| {
+                        (
+                            bytes(48..64),
+                            u32::from_le_bytes(record[64..68].try_into().unwrap()),
+                        )
+                    }),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the security level of the keys, as stored by the Bluetooth stack.
+    pub(crate) const fn security_level(&self) -> u8 {
+        if self.authenticated {
+            SEC_LEVEL_AUTHENTICATED
+        } else {
+            SEC_LEVEL_UNAUTHENTICATED
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<BondRecord> {
+        vec![
+            BondRecord {
+                address: BleAddress::public([0x11, 0x22, 0x33, 0x44, 0x55, 0x66]),
+                identity: None,
+                ltk: [0xA5; 16],
+                key_size: 16,
+                authenticated: false,
+                csrk: None,
+            },
+            BondRecord {
+                address: BleAddress::random([0x5A, 0x01, 0x02, 0x03, 0x04, 0x05]),
+                identity: Some((
+                    BleAddress::random([0xC0, 0xFF, 0xEE, 0x00, 0x11, 0x22]),
+                    [0x3C; 16],
+                )),
+                ltk: [0x0F; 16],
+                key_size: 7,
+                authenticated: true,
+                csrk: Some(([0x77; 16], 0x0102_0304)),
+            },
+        ]
+    }
+
+    fn blob() -> Vec<u8> {
+        BondRecord::to_blob(&records()).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let blob = blob();
+        assert_eq!(blob.len(), HEADER_SIZE + 2 * RECORD_SIZE);
+        assert_eq!(BondRecord::from_blob(&blob), Ok(records()));
+    }
+
+    #[test]
+    fn round_trip_without_records() {
+        let blob = BondRecord::to_blob(&[]).unwrap();
+        assert_eq!(BondRecord::from_blob(&blob), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut blob = blob();
+        blob[0] = b'X';
+        assert_eq!(
+            BondRecord::from_blob(&blob),
+            Err(BondBlobError::InvalidHeader)
+        );
+        assert_eq!(
+            BondRecord::from_blob(&blob[..3]),
+            Err(BondBlobError::InvalidHeader)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_version() {
+        let mut blob = blob();
+        blob[4] = BLOB_VERSION + 1;
+        assert_eq!(
+            BondRecord::from_blob(&blob),
+            Err(BondBlobError::UnsupportedVersion(BLOB_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_length() {
+        let blob = blob();
+        assert_eq!(
+            BondRecord::from_blob(&blob[..blob.len() - 1]),
+            Err(BondBlobError::InvalidLength)
+        );
+
+        let mut longer = blob;
+        longer.push(0);
+        assert_eq!(
+            BondRecord::from_blob(&longer),
+            Err(BondBlobError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_key_size() {
+        for key_size in [6, 17] {
+            let mut blob = blob();
+            blob[HEADER_SIZE + 24] = key_size;
+            assert_eq!(
+                BondRecord::from_blob(&blob),
+                Err(BondBlobError::InvalidRecord)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_flags() {
+        let mut blob = blob();
+        blob[HEADER_SIZE] |= 1 << 7;
+        assert_eq!(
+            BondRecord::from_blob(&blob),
+            Err(BondBlobError::InvalidRecord)
+        );
+    }
+}
@@ -1,5 +1,4 @@
 use esp_idf_sys::*;
-use log::warn;
 
 /// Represents the properties of a [`Characteristic`].
 ///
@@ -62,25 +61,19 @@ impl CharacteristicProperties {
     }
 
     /// Sets the "notify" property.
+    ///
+    /// It can be combined with the "indicate" property: each client then chooses in its CCCD.
     #[must_use]
-    pub fn notify(mut self) -> Self {
-        if self.indicate {
-            warn!("Cannot set notify and indicate at the same time. Ignoring notify.");
-            return self;
-        }
-
+    pub const fn notify(mut self) -> Self {
         self.notify = true;
         self
     }
 
     /// Sets the "indicate" property.
+    ///
+    /// It can be combined with the "notify" property: each client then chooses in its CCCD.
     #[must_use]
-    pub fn indicate(mut self) -> Self {
-        if self.notify {
-            warn!("Cannot set notify and indicate at the same time. Ignoring indicate.");
-            return self;
-        }
-
+    pub const fn indicate(mut self) -> Self {
         self.indicate = true;
         self
     }
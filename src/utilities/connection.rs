@@ -1,14 +1,158 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
 use esp_idf_sys::{
     esp_ble_gatts_cb_param_t_gatts_connect_evt_param,
     esp_ble_gatts_cb_param_t_gatts_disconnect_evt_param,
 };
+use lazy_static::lazy_static;
+
+use crate::{
+    gatt_server::{notification_stats, wait_for_room, GattServer},
+    utilities::{BleAddress, LinkSecurity, NotificationStats},
+};
+
+lazy_static! {
+    /// The connected clients and the security state of their links, by connection identifier.
+    ///
+    /// This is the only record of the connections, kept apart from the server so that
+    /// it can be queried without its lock, for example from the read and write callbacks.
+    static ref CONNECTIONS: Mutex<HashMap<u16, (Connection, LinkSecurity)>> =
+        Mutex::new(HashMap::new());
+}
 
+/// Represents a connection with a GATT client.
+///
+/// Connections are identified by their connection identifier, so that a peer
+/// reconnecting quickly is not confused with its previous link.
 #[derive(Debug, Copy, Clone)]
-pub(crate) struct Connection {
+pub struct Connection {
     pub(crate) id: u16,
     #[cfg(esp_idf_version_major = "4")]
     pub(crate) is_slave: bool,
     pub(crate) remote_bda: [u8; 6],
+    pub(crate) connected_at: Instant,
+}
+
+impl Connection {
+    /// Returns the connection identifier assigned by the Bluetooth stack.
+    #[must_use]
+    pub const fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// Returns the address of the peer, as seen over the air.
+    ///
+    /// This might be a resolvable private address.
+    #[must_use]
+    pub const fn remote_address(&self) -> [u8; 6] {
+        self.remote_bda
+    }
+
+    /// Returns the identity address of the peer, if it is bonded.
+    #[must_use]
+    pub fn identity_address(&self) -> Option<BleAddress> {
+        GattServer::resolve_address(BleAddress::public(self.remote_bda))
+    }
+
+    /// Returns whether the peer is bonded.
+    #[must_use]
+    pub fn is_bonded(&self) -> bool {
+        self.identity_address().is_some()
+    }
+
+    /// Returns the security state of the link.
+    #[must_use]
+    pub fn security(&self) -> LinkSecurity {
+        LinkSecurity::of(self.id).unwrap_or_default()
+    }
+
+    /// Returns the notification counters of the connection.
+    #[must_use]
+    pub fn notification_stats(&self) -> NotificationStats {
+        notification_stats(self.id).unwrap_or_default()
+    }
+
+    /// Waits until the notification queue of the connection has room, or the timeout expires.
+    ///
+    /// Use this with [`OverflowPolicy::Block`], after a notification was refused with
+    /// [`NotificationError::QueueFull`]. The lock of the characteristic must be released while waiting,
+    /// and this must not be called from a callback, because the queue is drained from the Bluetooth stack's context.
+    ///
+    /// Returns `false` if the queue is still full, or if the client disconnected.
+    ///
+    /// [`OverflowPolicy::Block`]: crate::utilities::OverflowPolicy::Block
+    /// [`NotificationError::QueueFull`]: crate::utilities::NotificationError::QueueFull
+    #[must_use]
+    pub fn wait_for_room(&self, timeout: Duration) -> bool {
+        wait_for_room(self.id, timeout)
+    }
+
+    /// Returns how long the connection has been up.
+    #[must_use]
+    pub fn connected_for(&self) -> Duration {
+        self.connected_at.elapsed()
+    }
+
+    /// Returns the connected clients.
+    pub(crate) fn connected() -> Vec<Self> {
+        CONNECTIONS
+            .lock()
+            .unwrap()
+            .values()
+            .map(|(connection, _)| *connection)
+            .collect()
+    }
+
+    /// Returns the number of connected clients.
+    pub(crate) fn count() -> usize {
+        CONNECTIONS.lock().unwrap().len()
+    }
+
+    /// Returns the connected client with the given connection identifier.
+    pub(crate) fn find(conn_id: u16) -> Option<Self> {
+        CONNECTIONS
+            .lock()
+            .unwrap()
+            .get(&conn_id)
+            .map(|(connection, _)| *connection)
+    }
+
+    /// Returns the security state of the link with a connected client.
+    pub(crate) fn security_of(conn_id: u16) -> Option<LinkSecurity> {
+        CONNECTIONS
+            .lock()
+            .unwrap()
+            .get(&conn_id)
+            .map(|(_, security)| *security)
+    }
+
+    /// Records the security state of the link with a connected client.
+    pub(crate) fn set_security(conn_id: u16, security: LinkSecurity) {
+        if let Some((_, current)) = CONNECTIONS.lock().unwrap().get_mut(&conn_id) {
+            *current = security;
+        }
+    }
+
+    /// Records a new connection, with an unencrypted link.
+    pub(crate) fn insert(self) {
+        CONNECTIONS
+            .lock()
+            .unwrap()
+            .insert(self.id, (self, LinkSecurity::default()));
+    }
+
+    /// Forgets a connection, and returns it if it was known.
+    pub(crate) fn remove(conn_id: u16) -> Option<Self> {
+        CONNECTIONS
+            .lock()
+            .unwrap()
+            .remove(&conn_id)
+            .map(|(connection, _)| connection)
+    }
 }
 
 impl From<esp_ble_gatts_cb_param_t_gatts_connect_evt_param> for Connection {
@@ -18,6 +162,7 @@ impl From<esp_ble_gatts_cb_param_t_gatts_connect_evt_param> for Connection {
             #[cfg(esp_idf_version_major = "4")]
             is_slave: param.link_role == 1,
             remote_bda: param.remote_bda,
+            connected_at: Instant::now(),
         }
     }
 }
@@ -29,6 +174,7 @@ impl From<esp_ble_gatts_cb_param_t_gatts_disconnect_evt_param> for Connection {
             #[cfg(esp_idf_version_major = "4")]
             is_slave: param.link_role == 1,
             remote_bda: param.remote_bda,
+            connected_at: Instant::now(),
         }
     }
 }
@@ -70,13 +216,13 @@ impl std::fmt::Display for Connection {
 
 impl std::hash::Hash for Connection {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.remote_bda.hash(state);
+        self.id.hash(state);
     }
 }
 
 impl PartialEq for Connection {
     fn eq(&self, other: &Self) -> bool {
-        self.remote_bda == other.remote_bda
+        self.id == other.id
     }
 }
 
@@ -0,0 +1,32 @@
+/// How a value change is pushed to a client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeliveryMode {
+    /// A notification, which the client does not acknowledge.
+    Notification,
+    /// An indication, which the client confirms.
+    #[default]
+    Indication,
+}
+
+/// What a client enabled in the CCCD of a characteristic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionMode {
+    /// The client enabled notifications.
+    Notifications,
+    /// The client enabled indications.
+    Indications,
+    /// The client enabled both notifications and indications.
+    Both,
+}
+
+impl SubscriptionMode {
+    /// Creates a [`SubscriptionMode`] from the bits of a CCCD, or `None` if the client is not subscribed.
+    pub(crate) const fn from_cccd(notifications: bool, indications: bool) -> Option<Self> {
+        match (notifications, indications) {
+            (true, true) => Some(Self::Both),
+            (true, false) => Some(Self::Notifications),
+            (false, true) => Some(Self::Indications),
+            (false, false) => None,
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use esp_idf_sys::{
+    esp_gatt_conn_reason_t, esp_gatt_conn_reason_t_ESP_GATT_CONN_FAIL_ESTABLISH,
+    esp_gatt_conn_reason_t_ESP_GATT_CONN_LMP_TIMEOUT,
+    esp_gatt_conn_reason_t_ESP_GATT_CONN_TERMINATE_LOCAL_HOST,
+    esp_gatt_conn_reason_t_ESP_GATT_CONN_TERMINATE_PEER_USER,
+    esp_gatt_conn_reason_t_ESP_GATT_CONN_TIMEOUT,
+};
+
+/// The reason of a disconnection, as reported by the Bluetooth stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The link supervision timeout expired, usually because the peer went out of range.
+    Timeout,
+    /// The peer closed the connection.
+    RemoteTerminated,
+    /// This device closed the connection.
+    LocalTerminated,
+    /// The connection could not be established.
+    FailedToEstablish,
+    /// Any other reason, with the raw reason code.
+    Other(u32),
+}
+
+impl From<esp_gatt_conn_reason_t> for DisconnectReason {
+    fn from(reason: esp_gatt_conn_reason_t) -> Self {
+        #[allow(non_upper_case_globals)]
+        match reason {
+            esp_gatt_conn_reason_t_ESP_GATT_CONN_TIMEOUT
+            | esp_gatt_conn_reason_t_ESP_GATT_CONN_LMP_TIMEOUT => Self::Timeout,
+            esp_gatt_conn_reason_t_ESP_GATT_CONN_TERMINATE_PEER_USER => Self::RemoteTerminated,
+            esp_gatt_conn_reason_t_ESP_GATT_CONN_TERMINATE_LOCAL_HOST => Self::LocalTerminated,
+            esp_gatt_conn_reason_t_ESP_GATT_CONN_FAIL_ESTABLISH => Self::FailedToEstablish,
+            other => Self::Other(other),
+        }
+    }
+}
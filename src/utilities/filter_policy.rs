@@ -0,0 +1,37 @@
+use esp_idf_sys::{
+    esp_ble_adv_filter_t, esp_ble_adv_filter_t_ADV_FILTER_ALLOW_SCAN_ANY_CON_ANY,
+    esp_ble_adv_filter_t_ADV_FILTER_ALLOW_SCAN_ANY_CON_WLST,
+    esp_ble_adv_filter_t_ADV_FILTER_ALLOW_SCAN_WLST_CON_ANY,
+    esp_ble_adv_filter_t_ADV_FILTER_ALLOW_SCAN_WLST_CON_WLST,
+};
+
+/// The advertising filter policy, deciding which devices can scan and connect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterPolicy {
+    /// Any device can scan and connect.
+    #[default]
+    AllowAll,
+    /// Only devices in the filter accept list can request a scan response, any device can connect.
+    ScanAcceptListOnly,
+    /// Any device can request a scan response, only devices in the filter accept list can connect.
+    ConnectAcceptListOnly,
+    /// Only devices in the filter accept list can scan and connect.
+    AcceptListOnly,
+}
+
+impl From<FilterPolicy> for esp_ble_adv_filter_t {
+    fn from(policy: FilterPolicy) -> Self {
+        match policy {
+            FilterPolicy::AllowAll => esp_ble_adv_filter_t_ADV_FILTER_ALLOW_SCAN_ANY_CON_ANY,
+            FilterPolicy::ScanAcceptListOnly => {
+                esp_ble_adv_filter_t_ADV_FILTER_ALLOW_SCAN_WLST_CON_ANY
+            }
+            FilterPolicy::ConnectAcceptListOnly => {
+                esp_ble_adv_filter_t_ADV_FILTER_ALLOW_SCAN_ANY_CON_WLST
+            }
+            FilterPolicy::AcceptListOnly => {
+                esp_ble_adv_filter_t_ADV_FILTER_ALLOW_SCAN_WLST_CON_WLST
+            }
+        }
+    }
+}
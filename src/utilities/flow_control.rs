@@ -0,0 +1,95 @@
+/// What happens to a notification when the transmit queue of a client is full.
+///
+/// See [`FlowControl`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued notification, so that the client receives the latest values.
+    #[default]
+    DropOldest,
+    /// Drop the new notification.
+    DropNewest,
+    /// Keep the queue and leave the new notification to the sender, so that it can wait for room.
+    ///
+    /// [`Characteristic::notify_to`] returns [`NotificationError::QueueFull`], and [`Characteristic::notify`]
+    /// skips the clients whose queue is full. The sender can then wait with [`Connection::wait_for_room`]
+    /// and try again, once it released the lock of the characteristic. Notifications triggered by
+    /// [`Characteristic::set_value`] cannot wait, so they are dropped and counted as such,
+    /// and the queued notifications are kept.
+    ///
+    /// [`Characteristic::notify`]: crate::gatt_server::Characteristic::notify
+    /// [`Characteristic::notify_to`]: crate::gatt_server::Characteristic::notify_to
+    /// [`Characteristic::set_value`]: crate::gatt_server::Characteristic::set_value
+    /// [`NotificationError::QueueFull`]: crate::utilities::NotificationError::QueueFull
+    /// [`Connection::wait_for_room`]: crate::utilities::Connection::wait_for_room
+    Block,
+}
+
+/// The flow control of notifications.
+///
+/// When a link is congested, notifications wait in a bounded per-connection queue,
+/// and are sent once the congestion clears.
+///
+/// See [`GattServer::flow_control`].
+///
+/// [`GattServer::flow_control`]: crate::gatt_server::GattServer::flow_control
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlowControl {
+    pub(crate) capacity: usize,
+    pub(crate) policy: OverflowPolicy,
+}
+
+impl FlowControl {
+    /// Creates a new [`FlowControl`], with a queue of 8 notifications per connection
+    /// that drops the oldest notification when full.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            capacity: 8,
+            policy: OverflowPolicy::DropOldest,
+        }
+    }
+
+    /// Sets how many notifications can wait in the queue of each connection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is 0.
+    #[must_use]
+    pub const fn capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "The queue capacity must be at least 1.");
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets what happens to a notification when the queue is full.
+    #[must_use]
+    pub const fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+impl Default for FlowControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The notification counters of a connection.
+///
+/// See [`Connection::notification_stats`].
+///
+/// [`Connection::notification_stats`]: crate::utilities::Connection::notification_stats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NotificationStats {
+    /// The notifications handed to the Bluetooth stack.
+    pub sent: u64,
+    /// The notifications dropped because the queue was full.
+    pub dropped: u64,
+    /// The notifications that the Bluetooth stack failed to send.
+    pub failed: u64,
+    /// The notifications currently waiting in the queue.
+    pub queued: usize,
+    /// Whether the link is currently congested.
+    pub congested: bool,
+}
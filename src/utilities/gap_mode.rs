@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+/// The GAP discoverability and connectability mode of the device, as defined in the Core specification, Vol. 3, Part C, 9.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GapMode {
+    /// Discoverable by any scanner, for an unlimited time, and connectable by anyone.
+    #[default]
+    GeneralDiscoverable,
+    /// Discoverable by any scanner and connectable by anyone, for at most 180 seconds.
+    ///
+    /// When the timeout expires, the device goes back to the mode it was in before.
+    /// This is the mode usually entered by pressing a pairing button.
+    LimitedDiscoverable,
+    /// Not discoverable, and only bonded peers in the filter accept list can connect or scan.
+    NonDiscoverable,
+    /// Advertising only, without accepting any connection.
+    NonConnectable,
+}
+
+/// The maximum time that a device can stay in limited discoverable mode, `TGAP(lim_adv_timeout)`.
+pub(crate) const LIMITED_DISCOVERABLE_TIMEOUT: Duration = Duration::from_secs(180);
@@ -0,0 +1,84 @@
+use std::{
+    sync::{mpsc, Arc},
+    time::Duration,
+};
+
+use crate::utilities::Connection;
+
+/// How long a client has to confirm an indication, as defined by the ATT protocol.
+pub(crate) const INDICATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub(crate) type IndicationCallback = dyn Fn(Connection, IndicationOutcome) + Send + Sync;
+
+/// The outcome of an indication sent to a client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndicationOutcome {
+    /// The client confirmed the indication.
+    Confirmed,
+    /// The Bluetooth stack could not send the indication.
+    Failed,
+    /// The client did not confirm the indication, or an earlier one on the same connection, within 30 seconds.
+    ///
+    /// No more indications are sent on the connection after a timeout, as required by the ATT protocol.
+    TimedOut,
+    /// The client disconnected before confirming the indication.
+    Disconnected,
+}
+
+/// Receives the outcomes of the indications triggered by a value change,
+/// one for every subscribed client.
+///
+/// Returned by [`Characteristic::set_value_with_receipt`].
+///
+/// [`Characteristic::set_value_with_receipt`]: crate::gatt_server::Characteristic::set_value_with_receipt
+#[derive(Debug)]
+pub struct IndicationReceipt(pub(crate) mpsc::Receiver<(Connection, IndicationOutcome)>);
+
+impl IndicationReceipt {
+    /// Blocks until every indication has completed, and returns the outcome for each client.
+    ///
+    /// Returns an empty list if no client was subscribed to indications.
+    ///
+    /// # Notes
+    ///
+    /// This must not be called from a callback, because the outcomes are reported from the Bluetooth stack's context.
+    #[must_use]
+    pub fn wait(self) -> Vec<(Connection, IndicationOutcome)> {
+        self.0.iter().collect()
+    }
+
+    /// Returns the outcomes reported so far, without blocking.
+    #[must_use]
+    pub fn try_outcomes(&self) -> Vec<(Connection, IndicationOutcome)> {
+        self.0.try_iter().collect()
+    }
+}
+
+/// Where the outcome of an indication is reported.
+#[derive(Clone, Default)]
+pub(crate) struct IndicationListener {
+    pub(crate) callback: Option<Arc<IndicationCallback>>,
+    pub(crate) receipt: Option<mpsc::Sender<(Connection, IndicationOutcome)>>,
+}
+
+impl IndicationListener {
+    pub(crate) fn report(&self, connection: Connection, outcome: IndicationOutcome) {
+        if let Some(callback) = &self.callback {
+            callback(connection, outcome);
+        }
+
+        if let Some(receipt) = &self.receipt {
+            // The receipt might have been dropped by the application.
+            let _ = receipt.send((connection, outcome));
+        }
+    }
+}
+
+impl std::fmt::Debug for IndicationListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IndicationListener")
+            .field("callback", &self.callback.is_some())
+            .field("receipt", &self.receipt.is_some())
+            .finish()
+    }
+}
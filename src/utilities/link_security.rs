@@ -0,0 +1,50 @@
+use esp_idf_sys::*;
+
+use crate::utilities::Connection;
+
+/// The security state of the link with a client.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LinkSecurity {
+    /// Whether the link is encrypted.
+    pub encrypted: bool,
+    /// Whether the encryption key comes from an authenticated (MITM-protected) pairing.
+    pub authenticated: bool,
+    /// Whether the encryption key comes from an LE Secure Connections pairing.
+    pub secure_connections: bool,
+    /// Whether the client is bonded, so the encryption key is stored with the bond.
+    pub bonded: bool,
+    /// The size of the encryption key in bytes, if known.
+    pub key_size: Option<u8>,
+}
+
+impl LinkSecurity {
+    /// Returns the security state of a connection, by connection identifier.
+    ///
+    /// This can be used in read and write callbacks, with the `conn_id` of the event parameter.
+    /// Returns `None` if the connection is unknown.
+    #[must_use]
+    pub fn of(conn_id: u16) -> Option<Self> {
+        Connection::security_of(conn_id)
+    }
+
+    /// Creates the security state of a link from the authentication mode negotiated by the Bluetooth stack.
+    ///
+    /// Whether the client is bonded comes from the bonds stored by the Bluetooth stack,
+    /// because the authentication mode only tells whether bonding was requested.
+    pub(crate) fn from_auth_mode(
+        auth_mode: esp_ble_auth_req_t,
+        key_size: Option<u8>,
+        bonded: bool,
+    ) -> Self {
+        let auth_mode = u32::from(auth_mode);
+
+        Self {
+            encrypted: true,
+            authenticated: auth_mode & ESP_LE_AUTH_REQ_MITM != 0,
+            secure_connections: auth_mode & ESP_LE_AUTH_REQ_SC_ONLY != 0,
+            bonded,
+            key_size,
+        }
+    }
+}
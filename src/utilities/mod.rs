@@ -7,9 +7,9 @@ pub(crate) mod leaky_box;
 mod attribute_control;
 pub(crate) use attribute_control::AttributeControl;
 
-// Connection: private.
+// Connection: public.
 mod connection;
-pub(crate) use connection::Connection;
+pub use connection::Connection;
 
 // BLE identifiers: public.
 mod ble_uuid;
@@ -23,6 +23,83 @@ pub use appearance::Appearance;
 mod characteristic_properties;
 pub use characteristic_properties::CharacteristicProperties;
 
+// Attribute authorization: public.
+mod authorization;
+pub(crate) use authorization::AuthorizationCallback;
+pub use authorization::{AttributeOperation, AuthorizationRequest};
+
+// Attribute access policies: public.
+mod access_policy;
+pub use access_policy::{AccessPolicy, AccessRule};
+
 // Attribute permissions: public.
 mod attribute_permissions;
-pub use attribute_permissions::AttributePermissions;
+pub use attribute_permissions::{AttributePermissions, ReadLevel, WriteLevel};
+
+// Device addresses: public.
+mod ble_address;
+pub use ble_address::{BleAddress, BleAddressType};
+
+// Address modes: public.
+mod address_mode;
+pub use address_mode::AddressMode;
+pub(crate) use address_mode::DEFAULT_RPA_TIMEOUT;
+
+// GAP modes: public.
+mod gap_mode;
+pub use gap_mode::GapMode;
+pub(crate) use gap_mode::LIMITED_DISCOVERABLE_TIMEOUT;
+
+// Advertising filter policies: public.
+mod filter_policy;
+pub use filter_policy::FilterPolicy;
+
+// Disconnection reasons: public.
+mod disconnect_reason;
+pub use disconnect_reason::DisconnectReason;
+
+// Reconnection policies: public.
+mod reconnection_policy;
+pub use reconnection_policy::ReconnectionPolicy;
+
+// Indications: public.
+mod indication;
+pub(crate) use indication::{IndicationCallback, IndicationListener, INDICATION_TIMEOUT};
+pub use indication::{IndicationOutcome, IndicationReceipt};
+
+// Delivery modes: public.
+mod delivery_mode;
+pub use delivery_mode::{DeliveryMode, SubscriptionMode};
+
+// Notification flow control: public.
+mod flow_control;
+pub use flow_control::{FlowControl, NotificationStats, OverflowPolicy};
+
+// Notification errors: public.
+mod notification_error;
+pub use notification_error::NotificationError;
+
+// Persistence backends: public.
+mod storage;
+pub use storage::{MemoryStorage, NvsStorage, Storage};
+
+// Link security: public.
+mod link_security;
+pub use link_security::LinkSecurity;
+
+// Bond records: public.
+mod bond_record;
+pub use bond_record::{BondBlobError, BondExport, BondRecord};
+
+// Security configuration: public.
+mod security_config;
+pub use security_config::{IoCapabilities, KeyDistribution, SecurityConfig};
+
+// Pairing requests: public.
+mod pairing;
+#[cfg(esp_idf_version_major = "5")]
+pub use pairing::ScOobRequest;
+pub use pairing::{
+    ConfirmationRequest, OobRequest, PairingEvent, PairingFailure, PasskeyRequest, ScOobData,
+};
+pub(crate) use pairing::{PendingReply, DEFAULT_PAIRING_TIMEOUT};
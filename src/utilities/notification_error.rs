@@ -0,0 +1,41 @@
+use esp_idf_sys::EspError;
+
+/// The reasons a notification or an indication cannot be sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationError {
+    /// The characteristic is not registered yet.
+    NotRegistered,
+    /// The characteristic does not have the notify or indicate property.
+    NotSupported,
+    /// The client has not enabled notifications or indications in its CCCD.
+    NotSubscribed,
+    /// The client is not connected.
+    NotConnected,
+    /// The notification was dropped, because the transmit queue of the client is full.
+    Dropped,
+    /// The notification was not sent, because the transmit queue of the client is full.
+    ///
+    /// Returned with [`OverflowPolicy::Block`]. See [`Connection::wait_for_room`].
+    ///
+    /// [`OverflowPolicy::Block`]: crate::utilities::OverflowPolicy::Block
+    /// [`Connection::wait_for_room`]: crate::utilities::Connection::wait_for_room
+    QueueFull,
+    /// The Bluetooth stack could not send the value.
+    Stack(EspError),
+}
+
+impl std::fmt::Display for NotificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotRegistered => write!(f, "the characteristic is not registered"),
+            Self::NotSupported => write!(f, "the characteristic does not support this operation"),
+            Self::NotSubscribed => write!(f, "the client is not subscribed"),
+            Self::NotConnected => write!(f, "the client is not connected"),
+            Self::Dropped => write!(f, "the transmit queue of the client is full"),
+            Self::QueueFull => write!(f, "the transmit queue of the client is full, try again"),
+            Self::Stack(error) => write!(f, "the Bluetooth stack cannot send the value: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for NotificationError {}
@@ -0,0 +1,331 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use esp_idf_sys::*;
+use log::{debug, warn};
+
+use crate::utilities::BleAddress;
+
+/// The default time the application has to answer a pairing request.
+pub(crate) const DEFAULT_PAIRING_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// An interactive step of a pairing procedure, reported to the application.
+#[derive(Debug)]
+pub enum PairingEvent {
+    /// The passkey must be shown to the user, who will type it on the peer.
+    DisplayPasskey {
+        /// The peer being paired.
+        peer: BleAddress,
+        /// The six-digit passkey.
+        passkey: u32,
+    },
+    /// The user must type the passkey shown on the peer.
+    PasskeyRequest(PasskeyRequest),
+    /// The user must confirm that both devices show the same number.
+    NumericComparison(ConfirmationRequest),
+    /// Out-of-band data must be provided for the peer.
+    OobRequest(OobRequest),
+    /// LE Secure Connections out-of-band data must be provided for the peer.
+    ///
+    /// Only available with ESP-IDF 5 and later.
+    #[cfg(esp_idf_version_major = "5")]
+    ScOobRequest(ScOobRequest),
+}
+
+/// LE Secure Connections out-of-band data: the confirm and random values of a device.
+///
+/// It is exchanged out of band, for example through an NFC tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScOobData {
+    /// The confirm value.
+    pub confirm: [u8; 16],
+    /// The random value.
+    pub random: [u8; 16],
+}
+
+/// Shared state of a pending pairing request, so that it is answered exactly once.
+#[derive(Debug, Clone)]
+pub(crate) struct PendingReply {
+    peer: BleAddress,
+    answered: Arc<AtomicBool>,
+}
+
+impl PendingReply {
+    pub(crate) fn new(peer: BleAddress) -> Self {
+        Self {
+            peer,
+            answered: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub(crate) fn is_answered(&self) -> bool {
+        self.answered.load(Ordering::SeqCst)
+    }
+
+    /// Marks the request as answered, returning `false` if it already was.
+    fn answer(&self) -> bool {
+        if self.answered.swap(true, Ordering::SeqCst) {
+            warn!(
+                "Pairing request from {} was already answered or timed out.",
+                self.peer
+            );
+            return false;
+        }
+
+        true
+    }
+
+    /// Logs a reply that the Bluetooth stack refused, for example because the pairing was already torn down.
+    fn report(&self, result: Result<(), EspError>) {
+        if let Err(error) = result {
+            warn!(
+                "Cannot answer pairing request from {}: {}.",
+                self.peer, error
+            );
+        }
+    }
+
+    pub(crate) fn passkey_reply(&self, accept: bool, passkey: u32) {
+        if self.answer() {
+            debug!("Answering passkey request from {}.", self.peer);
+            let mut address = self.peer.bytes();
+            let result =
+                unsafe { esp!(esp_ble_passkey_reply(address.as_mut_ptr(), accept, passkey)) };
+            self.report(result);
+        }
+    }
+
+    pub(crate) fn confirm_reply(&self, accept: bool) {
+        if self.answer() {
+            debug!("Answering numeric comparison from {}.", self.peer);
+            let mut address = self.peer.bytes();
+            let result = unsafe { esp!(esp_ble_confirm_reply(address.as_mut_ptr(), accept)) };
+            self.report(result);
+        }
+    }
+
+    pub(crate) fn oob_reply(&self, tk: Option<[u8; 16]>) {
+        if self.answer() {
+            debug!("Answering OOB request from {}.", self.peer);
+            let mut address = self.peer.bytes();
+            // An empty key rejects the request.
+            let (mut tk, len) = tk.map_or(([0u8; 16], 0), |tk| (tk, 16));
+            let result = unsafe {
+                esp!(esp_ble_oob_req_reply(
+                    address.as_mut_ptr(),
+                    tk.as_mut_ptr(),
+                    len
+                ))
+            };
+            self.report(result);
+        }
+    }
+
+    #[cfg(esp_idf_version_major = "5")]
+    pub(crate) fn sc_oob_reply(&self, data: ScOobData) {
+        if self.answer() {
+            debug!(
+                "Answering Secure Connections OOB request from {}.",
+                self.peer
+            );
+            let mut address = self.peer.bytes();
+            let mut data = data;
+            let result = unsafe {
+                esp!(esp_ble_sc_oob_req_reply(
+                    address.as_mut_ptr(),
+                    data.confirm.as_mut_ptr(),
+                    data.random.as_mut_ptr()
+                ))
+            };
+            self.report(result);
+        }
+    }
+
+    /// Rejects a Secure Connections OOB request by disconnecting the peer.
+    ///
+    /// The Bluetooth stack has no explicit rejection, and made-up values would fail the pairing
+    /// as if the peer had sent a wrong confirm value.
+    #[cfg(esp_idf_version_major = "5")]
+    pub(crate) fn sc_oob_reject(&self) {
+        if self.answer() {
+            debug!(
+                "Rejecting Secure Connections OOB request from {}.",
+                self.peer
+            );
+            let mut address = self.peer.bytes();
+            let result = unsafe { esp!(esp_ble_gap_disconnect(address.as_mut_ptr())) };
+            self.report(result);
+        }
+    }
+}
+
+/// A request to enter the passkey displayed on the peer.
+///
+/// It can be answered from any thread. If it is not answered within the pairing timeout,
+/// it is rejected.
+#[derive(Debug)]
+pub struct PasskeyRequest(pub(crate) PendingReply);
+
+impl PasskeyRequest {
+    /// Returns the peer being paired.
+    #[must_use]
+    pub fn peer(&self) -> BleAddress {
+        self.0.peer
+    }
+
+    /// Answers with the passkey typed by the user.
+    pub fn reply(self, passkey: u32) {
+        self.0.passkey_reply(true, passkey);
+    }
+
+    /// Rejects the pairing.
+    pub fn reject(self) {
+        self.0.passkey_reply(false, 0);
+    }
+}
+
+/// A request to confirm that the number shown on both devices is the same.
+///
+/// It can be answered from any thread. If it is not answered within the pairing timeout,
+/// it is rejected.
+#[derive(Debug)]
+pub struct ConfirmationRequest(pub(crate) PendingReply, pub(crate) u32);
+
+impl ConfirmationRequest {
+    /// Returns the peer being paired.
+    #[must_use]
+    pub fn peer(&self) -> BleAddress {
+        self.0.peer
+    }
+
+    /// Returns the six-digit number to show to the user.
+    #[must_use]
+    pub const fn passkey(&self) -> u32 {
+        self.1
+    }
+
+    /// Confirms that the numbers match.
+    pub fn confirm(self) {
+        self.0.confirm_reply(true);
+    }
+
+    /// Rejects the pairing.
+    pub fn reject(self) {
+        self.0.confirm_reply(false);
+    }
+}
+
+/// A request for the out-of-band temporary key shared with the peer.
+///
+/// It can be answered from any thread. If it is not answered within the pairing timeout,
+/// it is rejected.
+#[derive(Debug)]
+pub struct OobRequest(pub(crate) PendingReply);
+
+impl OobRequest {
+    /// Returns the peer being paired.
+    #[must_use]
+    pub fn peer(&self) -> BleAddress {
+        self.0.peer
+    }
+
+    /// Answers with the temporary key exchanged out of band.
+    pub fn reply(self, tk: [u8; 16]) {
+        self.0.oob_reply(Some(tk));
+    }
+
+    /// Rejects the pairing.
+    pub fn reject(self) {
+        self.0.oob_reply(None);
+    }
+}
+
+/// A request for the LE Secure Connections out-of-band data of the peer.
+///
+/// It can be answered from any thread. If it is not answered within the pairing timeout,
+/// it is rejected.
+///
+/// Only available with ESP-IDF 5 and later.
+#[cfg(esp_idf_version_major = "5")]
+#[derive(Debug)]
+pub struct ScOobRequest(pub(crate) PendingReply);
+
+#[cfg(esp_idf_version_major = "5")]
+impl ScOobRequest {
+    /// Returns the peer being paired.
+    #[must_use]
+    pub fn peer(&self) -> BleAddress {
+        self.0.peer
+    }
+
+    /// Answers with the confirm and random values of the peer, received out of band.
+    pub fn reply(self, data: ScOobData) {
+        self.0.sc_oob_reply(data);
+    }
+
+    /// Rejects the pairing.
+    ///
+    /// The Bluetooth stack has no explicit rejection, so the peer is disconnected.
+    pub fn reject(self) {
+        self.0.sc_oob_reject();
+    }
+}
+
+/// The reason a pairing procedure failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairingFailure {
+    /// The user did not enter a passkey, or cancelled the pairing.
+    PasskeyEntryFailed,
+    /// The out-of-band data is not available.
+    OobNotAvailable,
+    /// The authentication requirements cannot be met with the IO capabilities of the devices.
+    AuthenticationRequirements,
+    /// The confirm value does not match.
+    ConfirmValueFailed,
+    /// The peer does not support pairing.
+    PairingNotSupported,
+    /// The encryption key is too short.
+    EncryptionKeySize,
+    /// A command was not supported.
+    CommandNotSupported,
+    /// The pairing failed for an unspecified reason.
+    Unspecified,
+    /// Pairing was attempted too many times in a row.
+    RepeatedAttempts,
+    /// A pairing command had invalid parameters.
+    InvalidParameters,
+    /// The Diffie-Hellman key check failed.
+    DhKeyCheckFailed,
+    /// The numbers shown on the devices did not match.
+    NumericComparisonFailed,
+    /// The peer did not answer in time.
+    Timeout,
+    /// Another reason, as reported by the Bluetooth stack.
+    Other(u8),
+}
+
+impl From<u8> for PairingFailure {
+    fn from(reason: u8) -> Self {
+        match reason {
+            0x01 => Self::PasskeyEntryFailed,
+            0x02 => Self::OobNotAvailable,
+            0x03 => Self::AuthenticationRequirements,
+            0x04 => Self::ConfirmValueFailed,
+            0x05 => Self::PairingNotSupported,
+            0x06 => Self::EncryptionKeySize,
+            0x07 => Self::CommandNotSupported,
+            0x08 => Self::Unspecified,
+            0x09 => Self::RepeatedAttempts,
+            0x0A => Self::InvalidParameters,
+            0x0B => Self::DhKeyCheckFailed,
+            0x0C => Self::NumericComparisonFailed,
+            0x16 => Self::Timeout,
+            other => Self::Other(other),
+        }
+    }
+}
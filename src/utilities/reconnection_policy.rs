@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+/// What the server does with the advertisement after a client disconnects.
+///
+/// See [`GattServer::reconnection_policy`] and [`GattServer::on_disconnect_policy`].
+///
+/// [`GattServer::reconnection_policy`]: crate::gatt_server::GattServer::reconnection_policy
+/// [`GattServer::on_disconnect_policy`]: crate::gatt_server::GattServer::on_disconnect_policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReconnectionPolicy {
+    /// Do not advertise again.
+    Stop,
+    /// Restart advertising immediately, with the normal parameters.
+    #[default]
+    Restart,
+    /// Advertise only to the peer that just disconnected, for the given duration,
+    /// then restart advertising with the normal parameters.
+    ///
+    /// Only bonded peers can be targeted, because their address type is known.
+    /// For the other peers, advertising restarts right away.
+    Directed {
+        /// How long to keep the directed advertisement running.
+        duration: Duration,
+    },
+    /// Restart advertising after a delay that doubles on every disconnection, up to a maximum.
+    ///
+    /// The delay is reset when a connection stays up for longer than the maximum delay.
+    Backoff {
+        /// The delay after the first disconnection.
+        initial: Duration,
+        /// The maximum delay.
+        max: Duration,
+    },
+    /// Restart advertising immediately, but only while the number of connected clients is below the limit.
+    WhileBelow(usize),
+}
@@ -0,0 +1,294 @@
+use esp_idf_sys::*;
+
+/// The input and output capabilities of the device, used to choose the pairing method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IoCapabilities {
+    /// The device can display a passkey, but has no input.
+    DisplayOnly,
+    /// The device can display a passkey and the user can confirm it with a yes/no input.
+    DisplayYesNo,
+    /// The device has a keyboard to enter a passkey, but no display.
+    KeyboardOnly,
+    /// The device has neither input nor output. Pairing uses "Just Works".
+    #[default]
+    NoInputNoOutput,
+    /// The device has both a keyboard and a display.
+    KeyboardDisplay,
+}
+
+impl From<IoCapabilities> for esp_ble_io_cap_t {
+    #[allow(clippy::cast_possible_truncation)]
+    fn from(capabilities: IoCapabilities) -> Self {
+        let result = match capabilities {
+            IoCapabilities::DisplayOnly => ESP_IO_CAP_OUT,
+            IoCapabilities::DisplayYesNo => ESP_IO_CAP_IO,
+            IoCapabilities::KeyboardOnly => ESP_IO_CAP_IN,
+            IoCapabilities::NoInputNoOutput => ESP_IO_CAP_NONE,
+            IoCapabilities::KeyboardDisplay => ESP_IO_CAP_KBDISP,
+        };
+
+        result as Self
+    }
+}
+
+/// The set of keys distributed during pairing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KeyDistribution {
+    encryption: bool,
+    identity: bool,
+    signing: bool,
+    link: bool,
+}
+
+impl KeyDistribution {
+    /// Creates an empty [`KeyDistribution`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Distributes the encryption key (LTK, EDIV and Rand).
+    #[must_use]
+    pub const fn encryption(mut self) -> Self {
+        self.encryption = true;
+        self
+    }
+
+    /// Distributes the identity resolving key and the identity address.
+    #[must_use]
+    pub const fn identity(mut self) -> Self {
+        self.identity = true;
+        self
+    }
+
+    /// Distributes the connection signature resolving key.
+    #[must_use]
+    pub const fn signing(mut self) -> Self {
+        self.signing = true;
+        self
+    }
+
+    /// Derives the BR/EDR link key from the LE key.
+    #[must_use]
+    pub const fn link(mut self) -> Self {
+        self.link = true;
+        self
+    }
+}
+
+impl From<KeyDistribution> for u8 {
+    #[allow(clippy::cast_possible_truncation)]
+    fn from(keys: KeyDistribution) -> Self {
+        let mut result = 0;
+        if keys.encryption {
+            result |= ESP_BLE_ENC_KEY_MASK;
+        }
+        if keys.identity {
+            result |= ESP_BLE_ID_KEY_MASK;
+        }
+        if keys.signing {
+            result |= ESP_BLE_CSR_KEY_MASK;
+        }
+        if keys.link {
+            result |= ESP_BLE_LINK_KEY_MASK;
+        }
+        result as Self
+    }
+}
+
+/// The security and pairing configuration of the device.
+///
+/// The default configuration bonds with Secure Connections and "Just Works" pairing,
+/// and distributes the encryption and identity keys in both directions.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SecurityConfig {
+    pub(crate) io_capabilities: IoCapabilities,
+    pub(crate) bonding: bool,
+    pub(crate) mitm: bool,
+    pub(crate) secure_connections: bool,
+    pub(crate) secure_connections_only: bool,
+    pub(crate) min_key_size: u8,
+    pub(crate) max_key_size: u8,
+    pub(crate) initiator_keys: KeyDistribution,
+    pub(crate) responder_keys: KeyDistribution,
+    pub(crate) static_passkey: Option<u32>,
+    pub(crate) request_on_connect: bool,
+    pub(crate) oob: bool,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecurityConfig {
+    /// Creates a new [`SecurityConfig`] with the default settings.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            io_capabilities: IoCapabilities::NoInputNoOutput,
+            bonding: true,
+            mitm: false,
+            secure_connections: true,
+            secure_connections_only: false,
+            min_key_size: 7,
+            max_key_size: 16,
+            initiator_keys: KeyDistribution {
+                encryption: true,
+                identity: true,
+                signing: false,
+                link: false,
+            },
+            responder_keys: KeyDistribution {
+                encryption: true,
+                identity: true,
+                signing: false,
+                link: false,
+            },
+            static_passkey: None,
+            request_on_connect: false,
+            oob: false,
+        }
+    }
+
+    /// Sets the input and output capabilities of the device.
+    #[must_use]
+    pub const fn io_capabilities(mut self, capabilities: IoCapabilities) -> Self {
+        self.io_capabilities = capabilities;
+        self
+    }
+
+    /// Sets whether the keys are stored to bond with the peer.
+    #[must_use]
+    pub const fn bonding(mut self, bonding: bool) -> Self {
+        self.bonding = bonding;
+        self
+    }
+
+    /// Sets whether man-in-the-middle protection is required.
+    ///
+    /// This needs [`IoCapabilities`] that allow an authenticated pairing method.
+    #[must_use]
+    pub const fn mitm(mut self, mitm: bool) -> Self {
+        self.mitm = mitm;
+        self
+    }
+
+    /// Sets whether LE Secure Connections pairing is supported.
+    #[must_use]
+    pub const fn secure_connections(mut self, secure_connections: bool) -> Self {
+        self.secure_connections = secure_connections;
+        if !secure_connections {
+            self.secure_connections_only = false;
+        }
+        self
+    }
+
+    /// Rejects peers that do not support LE Secure Connections, or that do not meet
+    /// the configured authentication requirements.
+    #[must_use]
+    pub const fn secure_connections_only(mut self) -> Self {
+        self.secure_connections = true;
+        self.secure_connections_only = true;
+        self
+    }
+
+    /// Sets the range of accepted encryption key sizes, in bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is not within 7 to 16 bytes, or if `min` is greater than `max`.
+    #[must_use]
+    pub const fn key_size(mut self, min: u8, max: u8) -> Self {
+        assert!(
+            min >= 7 && max <= 16 && min <= max,
+            "Key sizes must be between 7 and 16 bytes."
+        );
+
+        self.min_key_size = min;
+        self.max_key_size = max;
+        self
+    }
+
+    /// Sets the keys that the peer distributes to the device.
+    #[must_use]
+    pub const fn initiator_keys(mut self, keys: KeyDistribution) -> Self {
+        self.initiator_keys = keys;
+        self
+    }
+
+    /// Sets the keys that the device distributes to the peer.
+    #[must_use]
+    pub const fn responder_keys(mut self, keys: KeyDistribution) -> Self {
+        self.responder_keys = keys;
+        self
+    }
+
+    /// Uses a fixed six-digit passkey for passkey entry pairing, instead of a random one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the passkey has more than six digits.
+    #[must_use]
+    pub const fn static_passkey(mut self, passkey: u32) -> Self {
+        assert!(
+            passkey <= 999_999,
+            "The passkey must have at most six digits."
+        );
+
+        self.static_passkey = Some(passkey);
+        self
+    }
+
+    /// Asks the peer to encrypt the link as soon as it connects,
+    /// instead of waiting for it to access a protected attribute.
+    #[must_use]
+    pub const fn request_on_connect(mut self) -> Self {
+        self.request_on_connect = true;
+        self
+    }
+
+    /// Announces that out-of-band pairing data is available, for example from an NFC tag.
+    ///
+    /// Legacy pairing uses OOB data when both devices announce it, and LE Secure Connections
+    /// when at least one of them does. The data is requested through the pairing callback.
+    ///
+    /// LE Secure Connections OOB data is only supported with ESP-IDF 5 and later. With ESP-IDF 4,
+    /// which this crate builds with by default, only the legacy temporary key can be exchanged out of band:
+    /// see [`PairingEvent::OobRequest`].
+    ///
+    /// [`PairingEvent::OobRequest`]: crate::utilities::PairingEvent::OobRequest
+    #[must_use]
+    pub const fn oob(mut self) -> Self {
+        self.oob = true;
+        self
+    }
+
+    /// Returns the authentication requirements, as expected by the Bluetooth stack.
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn auth_req(&self) -> esp_ble_auth_req_t {
+        let mut result = 0;
+        if self.bonding {
+            result |= ESP_LE_AUTH_BOND;
+        }
+        if self.mitm {
+            result |= ESP_LE_AUTH_REQ_MITM;
+        }
+        if self.secure_connections {
+            result |= ESP_LE_AUTH_REQ_SC_ONLY;
+        }
+
+        result as esp_ble_auth_req_t
+    }
+
+    /// Returns the security action used to encrypt the link, as expected by the Bluetooth stack.
+    pub(crate) const fn encryption_action(&self) -> esp_ble_sec_act_t {
+        if self.mitm {
+            esp_ble_sec_act_t_ESP_BLE_SEC_ENCRYPT_MITM
+        } else {
+            esp_ble_sec_act_t_ESP_BLE_SEC_ENCRYPT_NO_MITM
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use esp_idf_svc::nvs::{EspNvs, EspNvsPartition, NvsDefault, NvsPartitionId};
+use esp_idf_sys::EspError;
+
+/// A key-value store for the state that the crate persists: the CCCDs of bonded clients,
+/// the bonded clients to tell that the attribute table changed, and the persistent values.
+///
+/// Keys are at most 15 characters long, so that they fit in an NVS namespace.
+///
+/// See [`GattServer::storage`].
+///
+/// [`GattServer::storage`]: crate::gatt_server::GattServer::storage
+pub trait Storage: Send {
+    /// Reads the value stored at `key`, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value cannot be read.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, EspError>;
+
+    /// Stores `value` at `key`, replacing the previous value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value cannot be written.
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), EspError>;
+
+    /// Removes the value stored at `key`, and returns whether there was one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value cannot be removed.
+    fn remove(&mut self, key: &str) -> Result<bool, EspError>;
+}
+
+/// A [`Storage`] in a namespace of an NVS partition.
+///
+/// Values survive reboots, but not a flash erase.
+pub struct NvsStorage<T: NvsPartitionId = NvsDefault>(EspNvs<T>);
+
+impl<T: NvsPartitionId> NvsStorage<T> {
+    /// Creates a new [`NvsStorage`] in `namespace` of an NVS partition taken by the application.
+    ///
+    /// The partition can be shared with the application, as long as the namespace is not.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the namespace cannot be opened.
+    pub fn new(partition: EspNvsPartition<T>, namespace: &str) -> Result<Self, EspError> {
+        Ok(Self(EspNvs::new(partition, namespace, true)?))
+    }
+}
+
+impl<T: NvsPartitionId> Storage for NvsStorage<T> {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, EspError> {
+        let Some(len) = self.0.len(key)? else {
+            return Ok(None);
+        };
+
+        let mut buf = vec![0; len];
+        Ok(self.0.get_raw(key, &mut buf)?.map(<[u8]>::to_vec))
+    }
+
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), EspError> {
+        self.0.set_raw(key, value).map(|_| ())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<bool, EspError> {
+        self.0.remove(key)
+    }
+}
+
+/// A [`Storage`] in RAM.
+///
+/// Values are lost on reboot. This lets the crate work without an NVS partition.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage(HashMap<String, Vec<u8>>);
+
+impl MemoryStorage {
+    /// Creates a new, empty [`MemoryStorage`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, EspError> {
+        Ok(self.0.get(key).cloned())
+    }
+
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), EspError> {
+        self.0.insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<bool, EspError> {
+        Ok(self.0.remove(key).is_some())
+    }
+}